@@ -0,0 +1,60 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+use dlms_cosem::Dlms;
+use smart_meter::Decoder;
+
+/// One reading split into two encrypted M-Bus long frames.
+const FRAMES: &[u8] = include_bytes!("fixtures/frames.bin");
+const KEY: [u8; 16] = [0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe, 0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe];
+const FRAMES_PER_READING: u64 = 2;
+const READINGS: usize = 100;
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+  unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    System.alloc(layout)
+  }
+
+  unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+    System.dealloc(ptr, layout)
+  }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+fn decode_all(capture: &[u8]) -> usize {
+  let mut decoder = Decoder::new(Dlms::new(KEY));
+  decoder.push(capture);
+
+  let mut readings = 0;
+  while let Some(obis_map) = decoder.decode().expect("failed to decode fixture") {
+    black_box(obis_map);
+    readings += 1;
+  }
+  readings
+}
+
+fn decode(c: &mut Criterion) {
+  let capture = FRAMES.repeat(READINGS);
+
+  let before = ALLOCATIONS.load(Ordering::Relaxed);
+  assert_eq!(decode_all(&capture), READINGS);
+  let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+  println!("allocations per reading: {}", allocations / READINGS);
+
+  let mut group = c.benchmark_group("decode");
+  group.throughput(Throughput::Elements(READINGS as u64 * FRAMES_PER_READING));
+  group.bench_function("frames", |b| b.iter(|| decode_all(black_box(&capture))));
+  group.finish();
+}
+
+criterion_group!(benches, decode);
+criterion_main!(benches);
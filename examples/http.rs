@@ -0,0 +1,114 @@
+//! Serve the latest reading as JSON until interrupted with Ctrl-C.
+//!
+//! Reader errors are logged, and the connection is reopened after it was lost
+//! or after repeated decryption failures.
+
+use std::error::Error;
+use std::env;
+use std::io::{self, Read};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use either::Either;
+use hex::FromHex;
+use tiny_http::{Header, Response, Server};
+
+use dlms_cosem::{Dlms, ObisCode};
+use smart_meter::{convert, to_json, Error as SmartMeterError, SerialConfig, SmartMeter};
+
+/// Reads must time out regularly so the reader thread can notice the shutdown request.
+const READ_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Number of consecutive decryption failures after which the connection is reopened.
+const WATCHDOG_THRESHOLD: usize = 5;
+
+fn open(url_or_path: &str) -> io::Result<impl Read + Send> {
+  Ok(if url_or_path.contains(':') {
+    let stream = TcpStream::connect(url_or_path)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    Either::Left(stream)
+  } else {
+    Either::Right(SerialConfig::default().builder(url_or_path)
+      .timeout(READ_TIMEOUT)
+      .open()?)
+  })
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let url_or_path = env::args().nth(1).unwrap_or("/dev/serial0".into());
+  let key = env::args().nth(2).expect("No key provided");
+  let key = <[u8; 16]>::from_hex(key).expect("Invalid key format");
+  let addr = env::args().nth(3).unwrap_or("0.0.0.0:8080".into());
+
+  let stream = open(&url_or_path)?;
+
+  let server = Arc::new(Server::http(&addr)?);
+  let running = Arc::new(AtomicBool::new(true));
+
+  {
+    let server = Arc::clone(&server);
+    let running = Arc::clone(&running);
+
+    ctrlc::set_handler(move || {
+      running.store(false, Ordering::SeqCst);
+      server.unblock();
+    })?;
+  }
+
+  let latest = Arc::new(Mutex::new(None));
+
+  let reader = {
+    let latest = Arc::clone(&latest);
+    let running = Arc::clone(&running);
+
+    thread::spawn(move || {
+      let mut smart_meter = SmartMeter::new(stream, Dlms::new(key))
+        .with_watchdog(WATCHDOG_THRESHOLD, move || open(&url_or_path))
+        .with_conversion(ObisCode::new(0, 0, 1, 0, 0, 255), convert::date_time)
+        .with_conversion(ObisCode::new(0, 0, 42, 0, 0, 255), convert::utf8_string)
+        .with_conversion(ObisCode::new(0, 0, 96, 1, 0, 255), convert::utf8_string);
+
+      while running.load(Ordering::SeqCst) {
+        match smart_meter.read_reading() {
+          Ok(reading) => *latest.lock().unwrap() = Some(to_json(&reading)),
+          Err(SmartMeterError::Io(err)) if matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => continue,
+          Err(err @ (SmartMeterError::Io(_) | SmartMeterError::NoProgress)) => {
+            eprintln!("Error: {}, reconnecting", err);
+            thread::sleep(READ_TIMEOUT);
+
+            if let Err(err) = smart_meter.reopen() {
+              eprintln!("Error: {}", err);
+            }
+          },
+          Err(err) => eprintln!("Error: {}", err),
+        }
+      }
+    })
+  };
+
+  println!("Listening on http://{}", addr);
+
+  let content_type = Header::from_bytes("Content-Type", "application/json").unwrap();
+
+  // This ends once the server is unblocked on shutdown.
+  for request in server.incoming_requests() {
+    let json = latest.lock().unwrap().clone();
+
+    let response = match json {
+      Some(json) => Response::from_string(json).with_header(content_type.clone()),
+      None => Response::from_string("no reading yet").with_status_code(503),
+    };
+
+    if let Err(err) = request.respond(response) {
+      eprintln!("Error: {}", err);
+    }
+  }
+
+  running.store(false, Ordering::SeqCst);
+  reader.join().expect("reader thread panicked");
+
+  Ok(())
+}
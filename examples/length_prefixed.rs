@@ -0,0 +1,96 @@
+//! Read from a bridge which does not forward M-Bus frames, but length-prefixed messages.
+//!
+//! Each message starts with a 2-byte big-endian length. By default, the payload is a raw,
+//! encrypted general-glo-ciphering APDU, which is decrypted with `decrypt_apdu` and decoded
+//! with `parse_registers`, so any framing can be supported without going through `SmartMeter`.
+//!
+//! With `--mbus`, the payload is an M-Bus frame instead, so only the prefixes need to be stripped
+//! using `LengthPrefixedTransport` before reading as usual.
+
+use std::error::Error;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::TcpStream;
+
+use either::Either;
+use hex::FromHex;
+
+use dlms_cosem::Dlms;
+use smart_meter::{decrypt_apdu, format_value, parse_registers, DuplicateCodes, Endian, Error as SmartMeterError, LengthPrefixedTransport, SmartMeter};
+
+/// Read the next message, or `None` at the end of the stream.
+fn read_message(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+  let mut len = [0; 2];
+  match reader.read_exact(&mut len) {
+    Ok(()) => (),
+    Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+    Err(err) => return Err(err),
+  }
+
+  let mut message = vec![0; usize::from(u16::from_be_bytes(len))];
+  reader.read_exact(&mut message)?;
+  Ok(Some(message))
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let mut args = env::args().skip(1).collect::<Vec<_>>();
+  let mbus = match args.iter().position(|arg| arg == "--mbus") {
+    Some(i) => {
+      args.remove(i);
+      true
+    },
+    None => false,
+  };
+
+  let url_or_path = args.first().cloned().unwrap_or("capture.bin".into());
+  let key = args.get(1).expect("No key provided");
+  let key = <[u8; 16]>::from_hex(key).expect("Invalid key format");
+
+  let mut stream = if url_or_path.contains(':') {
+    Either::Left(TcpStream::connect(url_or_path)?)
+  } else {
+    Either::Right(File::open(url_or_path)?)
+  };
+
+  if mbus {
+    let transport = LengthPrefixedTransport::new(stream, Endian::Big, 2);
+
+    for obis_map in SmartMeter::new(transport, Dlms::new(key)) {
+      let obis_map = match obis_map {
+        Ok(obis_map) => obis_map,
+        Err(SmartMeterError::NoProgress) => break,
+        Err(err) => {
+          eprintln!("Error: {}", err);
+          continue
+        },
+      };
+
+      for (code, reg) in obis_map.iter() {
+        println!("{:<16} {}", format!("{}:", code), format_value(reg.value()));
+      }
+      println!();
+    }
+
+    return Ok(())
+  }
+
+  while let Some(message) = read_message(&mut stream)? {
+    let registers = match decrypt_apdu(&message, key).and_then(|apdu| parse_registers(&apdu, DuplicateCodes::KeepLast)) {
+      Ok(registers) => registers,
+      Err(err) => {
+        eprintln!("Error: {}", err);
+        continue
+      },
+    };
+
+    for (code, regs) in registers {
+      for reg in regs {
+        println!("{:<16} {}", format!("{}:", code), format_value(reg.value()));
+      }
+    }
+    println!();
+  }
+
+  Ok(())
+}
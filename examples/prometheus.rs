@@ -0,0 +1,123 @@
+//! Serve the latest reading in the Prometheus text format on `/metrics`.
+//!
+//! Readings are converted using `to_prometheus`, see `PrometheusTextfile` for writing them to
+//! a file for the node exporter instead.
+//!
+//! The connection is reopened after it was lost or the meter stopped sending.
+
+use std::error::Error;
+use std::env;
+use std::io::{self, Read};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use either::Either;
+use hex::FromHex;
+use tiny_http::{Header, Response, Server};
+
+use dlms_cosem::{Dlms, ObisMap};
+use smart_meter::{to_prometheus, Error as SmartMeterError, SerialConfig, Sink, SinkError, SmartMeter};
+
+/// Reads time out so a lost connection is noticed and reopened.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Number of consecutive decryption failures after which the connection is reopened.
+const WATCHDOG_THRESHOLD: usize = 5;
+
+fn open(url_or_path: &str) -> io::Result<impl Read + Send> {
+  Ok(if url_or_path.contains(':') {
+    let stream = TcpStream::connect(url_or_path)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    Either::Left(stream)
+  } else {
+    Either::Right(SerialConfig::default().builder(url_or_path)
+      .timeout(READ_TIMEOUT)
+      .open()?)
+  })
+}
+
+/// The metrics of the latest reading, shared with the HTTP server.
+#[derive(Debug, Clone, Default)]
+struct Metrics(Arc<Mutex<Option<String>>>);
+
+impl Metrics {
+  /// Stop serving stale metrics.
+  fn clear(&self) {
+    *self.0.lock().unwrap() = None;
+  }
+
+  fn get(&self) -> Option<String> {
+    self.0.lock().unwrap().clone()
+  }
+}
+
+impl Sink for Metrics {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    *self.0.lock().unwrap() = Some(to_prometheus(reading));
+    Ok(())
+  }
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let url_or_path = env::args().nth(1).unwrap_or("/dev/serial0".into());
+  let key = env::args().nth(2).expect("No key provided");
+  let key = <[u8; 16]>::from_hex(key).expect("Invalid key format");
+  let addr = env::args().nth(3).unwrap_or("0.0.0.0:9100".into());
+
+  let stream = open(&url_or_path)?;
+
+  let metrics = Metrics::default();
+
+  {
+    let mut metrics = metrics.clone();
+
+    thread::spawn(move || {
+      let mut smart_meter = SmartMeter::new(stream, Dlms::new(key))
+        .with_watchdog(WATCHDOG_THRESHOLD, move || open(&url_or_path));
+
+      loop {
+        match smart_meter.read_reading() {
+          Ok(reading) => {
+            if let Err(err) = metrics.consume(&reading) {
+              eprintln!("Error: {}", err);
+            }
+          },
+          // The meter stopped sending or the connection was lost, so stop serving stale metrics.
+          Err(err @ (SmartMeterError::Io(_) | SmartMeterError::NoProgress)) => {
+            eprintln!("Error: {}, reconnecting", err);
+            metrics.clear();
+            thread::sleep(READ_TIMEOUT);
+
+            if let Err(err) = smart_meter.reopen() {
+              eprintln!("Error: {}", err);
+            }
+          },
+          Err(err) => eprintln!("Error: {}", err),
+        }
+      }
+    });
+  }
+
+  let server = Server::http(&addr)?;
+  println!("Serving metrics on http://{}/metrics", addr);
+
+  let content_type = Header::from_bytes("Content-Type", "text/plain; version=0.0.4").unwrap();
+
+  for request in server.incoming_requests() {
+    if request.url() != "/metrics" {
+      request.respond(Response::from_string("not found").with_status_code(404))?;
+      continue
+    }
+
+    let response = match metrics.get() {
+      Some(metrics) => Response::from_string(metrics).with_header(content_type.clone()),
+      None => Response::from_string("no reading yet").with_status_code(503),
+    };
+
+    request.respond(response)?;
+  }
+
+  Ok(())
+}
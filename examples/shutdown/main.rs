@@ -0,0 +1,45 @@
+//! Print readings until Ctrl-C is pressed, then stop the reader thread and exit cleanly.
+
+use std::error::Error;
+use std::env;
+use std::io;
+use std::net::TcpStream;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use either::Either;
+use hex::FromHex;
+
+use dlms_cosem::Dlms;
+use smart_meter::{SerialConfig, SmartMeter};
+
+mod run;
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let url_or_path = env::args().nth(1).unwrap_or("/dev/serial0".into());
+  let key = env::args().nth(2).expect("No key provided");
+  let key = <[u8; 16]>::from_hex(key).expect("Invalid key format");
+
+  // Reads must time out regularly so the shutdown request is noticed and the reader thread can exit.
+  let read_timeout = Duration::from_secs(1);
+
+  let stream = if url_or_path.contains(':') {
+    let stream = TcpStream::connect(url_or_path)?;
+    stream.set_read_timeout(Some(read_timeout))?;
+    Either::Left(stream)
+  } else {
+    Either::Right(SerialConfig::default().builder(url_or_path)
+      .timeout(read_timeout)
+      .open()?)
+  };
+
+  let running = Arc::new(AtomicBool::new(true));
+  {
+    let running = Arc::clone(&running);
+    ctrlc::set_handler(move || running.store(false, Ordering::SeqCst))?;
+  }
+
+  let handle = SmartMeter::new(stream, Dlms::new(key)).spawn(1);
+  run::print_readings(handle, &running, &mut io::stdout().lock())
+}
@@ -0,0 +1,33 @@
+use std::error::Error;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use smart_meter::{format_value, Error as SmartMeterError, ReaderHandle};
+
+/// Print readings until `running` is cleared or the reader is exhausted, then join the reader thread.
+///
+/// Reads must time out regularly, so `running` is checked even while the meter is silent.
+pub fn print_readings(mut handle: ReaderHandle, running: &AtomicBool, out: &mut impl Write) -> Result<(), Box<dyn Error + Send + Sync>> {
+  for item in handle.by_ref() {
+    if !running.load(Ordering::SeqCst) {
+      break
+    }
+
+    match item {
+      Ok(reading) => {
+        for (key, reg) in reading.iter() {
+          writeln!(out, "{:<16} {}", format!("{}:", key), format_value(reg.value()))?;
+        }
+        writeln!(out)?;
+      },
+      Err(SmartMeterError::Io(err)) if matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => continue,
+      Err(SmartMeterError::NoProgress) => break,
+      Err(err) => eprintln!("Error: {}", err),
+    }
+  }
+  out.flush()?;
+
+  handle.join().map_err(|_| "reader thread panicked")?;
+
+  Ok(())
+}
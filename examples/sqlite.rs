@@ -0,0 +1,96 @@
+//! Write readings to an SQLite database.
+//!
+//! Every register of a reading is stored as one row:
+//!
+//! ```sql
+//! CREATE TABLE registers (
+//!   received_at INTEGER NOT NULL, -- Unix time in seconds, when the reading was received.
+//!   meter_time  TEXT,             -- The meter clock (0-0:1.0.0), if reported.
+//!   code        TEXT NOT NULL,    -- The OBIS code, e.g. `1-0:1.8.0*255`.
+//!   value,                        -- The value, as REAL for numbers, otherwise as TEXT.
+//!   unit        TEXT              -- The unit, if any.
+//! );
+//! ```
+
+use std::error::Error;
+use std::env;
+use std::net::TcpStream;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use either::Either;
+use hex::FromHex;
+use rusqlite::{params, Connection};
+use rusqlite::types::Value;
+
+use dlms_cosem::{Data, Dlms, ObisCode};
+use smart_meter::{convert, SerialConfig, SmartMeter};
+
+const CLOCK: ObisCode = ObisCode::new(0, 0, 1, 0, 0, 255);
+
+fn to_sql(value: &Data) -> Value {
+  match *value {
+    Data::Integer(n) => Value::Real(n.into()),
+    Data::Unsigned(n) => Value::Real(n.into()),
+    Data::Long(n) => Value::Real(n.into()),
+    Data::LongUnsigned(n) => Value::Real(n.into()),
+    Data::DoubleLong(n) => Value::Real(n.into()),
+    Data::DoubleLongUnsigned(n) => Value::Real(n.into()),
+    Data::Long64(n) => Value::Real(n as f64),
+    Data::Long64Unsigned(n) => Value::Real(n as f64),
+    Data::Float32(n) => Value::Real(n.into()),
+    Data::Float64(n) => Value::Real(n),
+    ref value => Value::Text(smart_meter::format_value(value).into_owned()),
+  }
+}
+
+fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+  let url_or_path = env::args().nth(1).unwrap_or("/dev/serial0".into());
+  let key = env::args().nth(2).expect("No key provided");
+  let key = <[u8; 16]>::from_hex(key).expect("Invalid key format");
+  let db_path = env::args().nth(3).unwrap_or("readings.sqlite".into());
+
+  let stream = if url_or_path.contains(':') {
+    Either::Left(TcpStream::connect(url_or_path)?)
+  } else {
+    Either::Right(SerialConfig::default().builder(url_or_path)
+      .open()?)
+  };
+
+  let mut db = Connection::open(db_path)?;
+  db.execute_batch("
+    CREATE TABLE IF NOT EXISTS registers (
+      received_at INTEGER NOT NULL,
+      meter_time  TEXT,
+      code        TEXT NOT NULL,
+      value,
+      unit        TEXT
+    );
+  ")?;
+
+  let mut smart_meter = SmartMeter::new(stream, Dlms::new(key))
+    .with_conversion(ObisCode::new(0, 0, 42, 0, 0, 255), convert::utf8_string)
+    .with_conversion(ObisCode::new(0, 0, 96, 1, 0, 255), convert::utf8_string)
+    .with_conversion(CLOCK, convert::date_time);
+
+  loop {
+    let reading = smart_meter.read_reading()?;
+
+    let received_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let meter_time = reading.get(&CLOCK).map(|reg| smart_meter::format_value(reg.value()).into_owned());
+
+    let tx = db.transaction()?;
+    {
+      let mut insert = tx.prepare_cached(
+        "INSERT INTO registers (received_at, meter_time, code, value, unit) VALUES (?1, ?2, ?3, ?4, ?5)",
+      )?;
+
+      for (code, reg) in reading.iter().filter(|(code, _)| **code != CLOCK) {
+        let unit = reg.unit().and_then(|u| u.as_str());
+        insert.execute(params![received_at, meter_time, code.to_string(), to_sql(reg.value()), unit])?;
+      }
+    }
+    tx.commit()?;
+
+    println!("Stored {} registers.", reading.len());
+  }
+}
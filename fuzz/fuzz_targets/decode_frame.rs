@@ -0,0 +1,12 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use smart_meter::decode_frame;
+
+const KEY: [u8; 16] = [0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe, 0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe];
+
+// The default hook is kept, so panics are reported even if the crate catches them.
+fuzz_target!(|data: &[u8]| {
+  let _ = decode_frame(data, KEY);
+});
@@ -0,0 +1,29 @@
+#![no_main]
+
+use dlms_cosem::Dlms;
+use libfuzzer_sys::fuzz_target;
+
+use smart_meter::{Decoder, Reading};
+
+const KEY: [u8; 16] = [0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe, 0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe];
+
+fuzz_target!(
+  // Panics caught inside `Dlms::decrypt` for inputs which cannot be checked beforehand
+  // must not abort, only those escaping the crate. See the other targets for the rest.
+  init: drop(std::panic::take_hook()),
+  |data: &[u8]| {
+    let mut decoder = Decoder::new(Dlms::new(KEY)).lenient_start(true).baud_mismatch_threshold(None);
+    decoder.push(data);
+
+    loop {
+      match decoder.decode() {
+        Ok(Some(obis_map)) => {
+          let reading = Reading::from(obis_map);
+          let _ = (reading.timestamp(), reading.equipment_id(), reading.frequency_hz(), reading.power_factor());
+        },
+        Ok(None) => break,
+        Err(_) => continue,
+      }
+    }
+  }
+);
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+use smart_meter::{parse_load_profile, parse_max_demands, parse_registers, DataTree, DuplicateCodes};
+
+// The default hook is kept, so panics are reported even if the crate catches them.
+fuzz_target!(|data: &[u8]| {
+  let _ = DataTree::parse(data);
+  let _ = parse_max_demands(data);
+  let _ = parse_registers(data, DuplicateCodes::CollectAll);
+  let _ = parse_load_profile(data);
+});
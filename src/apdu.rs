@@ -0,0 +1,377 @@
+use std::panic::{self, AssertUnwindSafe};
+
+use dlms_cosem::{Apdu, Data, Error as DlmsError, ObisCode, ObisMap};
+use mbusparse::Telegram;
+
+use crate::{apply_scaler_f64, Error};
+use crate::tree::MAX_DEPTH;
+
+/// Reassemble the APDU bytes carried by one unsegmented or multiple segmented M-Bus telegrams.
+pub(crate) fn assemble_into(telegrams: &[Telegram<'_>], payload: &mut Vec<u8>) -> Result<(), DlmsError> {
+  payload.clear();
+
+  let mut current_segment = 0;
+
+  for telegram in telegrams {
+    let (control_information, user_data) = match telegram {
+      Telegram::LongFrame { control_information, user_data, .. } => (*control_information, *user_data),
+      _ => return Err(DlmsError::InvalidFormat),
+    };
+
+    let (header_len, last_segment) = match control_information {
+      0x00..=0x1f => {
+        if control_information & 0b1111 != current_segment {
+          return Err(DlmsError::ChecksumMismatch)
+        }
+        current_segment = current_segment.wrapping_add(1);
+
+        (0, (control_information & 0b10000) != 0)
+      },
+      // Long header with manufacturer ID, version and device type.
+      0x60 | 0x7c => (3 + 3, true),
+      0x61 | 0x7d => (3, true),
+      _ => return Err(DlmsError::InvalidFormat),
+    };
+
+    // Skip header and STSAP/DTSAP.
+    payload.extend(user_data.get((header_len + 2)..).ok_or(DlmsError::InvalidFormat)?);
+
+    if last_segment {
+      return Ok(())
+    }
+  }
+
+  Err(DlmsError::Incomplete(None))
+}
+
+/// Check whether decrypted bytes look like a data-notification APDU.
+///
+/// This checks the tag, the length of the date-time and the type of the notification body.
+pub(crate) fn is_data_notification(apdu: &[u8]) -> bool {
+  match apdu {
+    [0x0f, _, _, _, _, date_time_len @ (0x00 | 0x0c), rest @ ..] => {
+      matches!(rest.get(usize::from(*date_time_len)), Some(0x01 | 0x02))
+    },
+    _ => false,
+  }
+}
+
+/// Get the offset of the security control byte of a raw general-glo-ciphering APDU.
+fn security_header_offset(apdu: &[u8]) -> Option<usize> {
+  if apdu.first() != Some(&0xdb) {
+    return None
+  }
+
+  // Skip tag, system title and length. Like `dlms_cosem`, only 0x82 is read as a length prefix,
+  // any other byte is the length itself.
+  Some(match apdu.get(10)? {
+    0x82 => 13,
+    _ => 11,
+  })
+}
+
+/// Get the security control byte of a raw general-glo-ciphering APDU.
+pub(crate) fn security_control(apdu: &[u8]) -> Option<u8> {
+  apdu.get(security_header_offset(apdu)?).copied()
+}
+
+/// Get the invocation counter of a raw general-glo-ciphering APDU.
+pub(crate) fn invocation_counter(apdu: &[u8]) -> Option<u32> {
+  // Skip the security control byte.
+  let offset = security_header_offset(apdu)? + 1;
+
+  let counter = apdu.get(offset..(offset + 4))?;
+  Some(u32::from_be_bytes([counter[0], counter[1], counter[2], counter[3]]))
+}
+
+/// Check that an APDU is a data-notification or a general-glo-ciphering APDU
+/// which `dlms_cosem` can parse without panicking.
+///
+/// Data-notifications are checked completely, see [`is_decodable`].
+pub(crate) fn has_valid_header(apdu: &[u8]) -> bool {
+  match apdu.first() {
+    Some(0x0f) => return is_decodable(apdu),
+    Some(0xdb) => (),
+    _ => return false,
+  }
+
+  // The payload length includes the security header, which `dlms_cosem` subtracts unchecked.
+  let payload_len = match apdu.get(10) {
+    Some(0x82) => apdu.get(11..13).map(|len| u16::from_be_bytes([len[0], len[1]]).into()),
+    len => len.map(|&len| usize::from(len)),
+  };
+  matches!(payload_len, Some(len) if len >= 5)
+}
+
+/// Check that a plain APDU is a data-notification which `dlms_cosem` can parse without panicking.
+///
+/// Like `dlms_cosem`, lengths are a single byte. Only the data types it implements are accepted,
+/// nested at most [`MAX_DEPTH`] levels deep, and only scalers it can apply without overflowing.
+pub(crate) fn is_decodable(apdu: &[u8]) -> bool {
+  check_decodable(apdu).is_ok()
+}
+
+/// Like [`is_decodable`], but fails with [`Error::CompactArray`] if the first value which cannot be
+/// decoded is a compact array, and with [`Error::InvalidFormat`] otherwise.
+pub(crate) fn check_decodable(apdu: &[u8]) -> Result<(), Error> {
+  match apdu {
+    [0x0f, _, _, _, _, date_time_len, rest @ ..] => {
+      let body = rest.get(usize::from(*date_time_len)..).ok_or(Error::InvalidFormat)?;
+      skip_data(body, 0).map(|_| ())
+    },
+    _ => Err(Error::InvalidFormat),
+  }
+}
+
+/// Skip a single encoded data value, returning the remaining input.
+fn skip_data(input: &[u8], depth: usize) -> Result<&[u8], Error> {
+  if depth > MAX_DEPTH {
+    return Err(Error::InvalidFormat)
+  }
+
+  let (&tag, input) = input.split_first().ok_or(Error::InvalidFormat)?;
+
+  let len = match tag {
+    // Structure.
+    0x02 => {
+      let (&count, mut input) = input.split_first().ok_or(Error::InvalidFormat)?;
+
+      // Scaler and unit, scaled with an integer factor of `10^|scaler|`.
+      if let (2, [0x0f, scaler, 0x16, _, ..]) = (count, input) {
+        if !(-9..=9).contains(&(*scaler as i8)) {
+          return Err(Error::InvalidFormat)
+        }
+      }
+
+      for _ in 0..count {
+        input = skip_data(input, depth + 1)?;
+      }
+      return Ok(input)
+    },
+    // Octet string.
+    0x09 => {
+      let (&len, input) = input.split_first().ok_or(Error::InvalidFormat)?;
+      return input.get(usize::from(len)..).ok_or(Error::InvalidFormat)
+    },
+    0x00 => 0,
+    0x0f | 0x16 => 1,
+    0x10 | 0x12 => 2,
+    0x05 | 0x06 | 0x17 | 0x1b => 4,
+    0x1a => 5,
+    0x14 | 0x15 | 0x18 => 8,
+    0x19 => 12,
+    0x13 => return Err(Error::CompactArray),
+    _ => return Err(Error::InvalidFormat),
+  };
+
+  input.get(len..).ok_or(Error::InvalidFormat)
+}
+
+/// Call into `dlms_cosem`, turning its panics into `None`.
+///
+/// `dlms_cosem` uses `unimplemented!` for unknown APDU tags and data types and overflows
+/// on large scalers, all of which are reachable with malformed input or, since the plain text
+/// is not authenticated, a wrong key.
+///
+/// Prefer checking the input with [`is_decodable`] where the plain text is available. This is only
+/// a fallback where it is not, i.e. inside `Dlms::decrypt` and for already parsed `Apdu`s: the panic
+/// hook still runs, so the panic message is printed, and with `panic = "abort"` the process aborts.
+pub(crate) fn catch_unimplemented<T>(f: impl FnOnce() -> T) -> Option<T> {
+  panic::catch_unwind(AssertUnwindSafe(f)).ok()
+}
+
+/// Decrypt a raw general-glo-ciphering APDU, as returned by [`SmartMeter::last_apdu`](crate::SmartMeter::last_apdu).
+///
+/// The result is the plain APDU, usually a data-notification (tag `0x0f`) consisting of
+/// the long-invoke-id-and-priority, an optional date-time and the notification body.
+/// Unencrypted APDUs are returned unchanged. The body may contain compact arrays, which only
+/// [`DataTree`](crate::DataTree) can decode.
+pub fn decrypt_apdu(apdu: &[u8], key: [u8; 16]) -> Result<Vec<u8>, Error> {
+  if apdu.first() == Some(&0x0f) {
+    return Ok(apdu.to_vec())
+  }
+
+  if !has_valid_header(apdu) {
+    return Err(Error::InvalidFormat)
+  }
+
+  match Apdu::parse(apdu) {
+    Ok((_, Apdu::GeneralGloCiphering(ciphering))) => {
+      ciphering.decrypt(&key.into()).map_err(|_| Error::DecryptionFailed(None))
+    },
+    _ => Err(Error::InvalidFormat),
+  }
+}
+
+/// Apply the scalers of integer registers which `dlms_cosem` leaves unscaled.
+///
+/// Only long-unsigned and double-long-unsigned values are scaled while decoding,
+/// other integer types, notably the signed ones used e.g. for temperatures or power factors,
+/// keep their raw value. Given the plain APDU of a reading, see [`decrypt_apdu`], this
+/// converts these values to floats with the correct scale and sign.
+///
+/// `apdu` should be the APDU `obis_map` was decoded from, registers not contained in
+/// `obis_map` are ignored. Fails with [`Error::InvalidFormat`] if `apdu` cannot be decoded,
+/// or with [`Error::CompactArray`] if it contains a compact array.
+pub fn apply_scalers(obis_map: &mut ObisMap, apdu: &[u8]) -> Result<(), Error> {
+  check_decodable(apdu)?;
+
+  let body = match apdu {
+    [0x0f, _, _, _, _, date_time_len, rest @ ..] => &rest[usize::from(*date_time_len)..],
+    _ => return Err(Error::InvalidFormat),
+  };
+
+  let items = match Data::parse(body) {
+    Ok((_, Data::Structure(items))) => items,
+    _ => return Err(Error::InvalidFormat),
+  };
+
+  fn scaler(data: Option<&Data>) -> Option<i8> {
+    match data {
+      Some(Data::Structure(scaler_unit)) => match scaler_unit.as_slice() {
+        [Data::Integer(scaler), Data::Enum(unit)] if *scaler != 0x00 || *unit != 0xff => Some(*scaler),
+        _ => None,
+      },
+      _ => None,
+    }
+  }
+
+  fn register(items: &[Data]) -> Option<(ObisCode, Option<i8>, usize)> {
+    match items {
+      [Data::OctetString(code), _, rest @ ..] => {
+        let (_, code) = ObisCode::parse(code).ok()?;
+        let scaler = scaler(rest.first());
+        Some((code, scaler, if scaler.is_some() { 3 } else { 2 }))
+      },
+      [Data::Structure(inner), ..] => {
+        let (code, scaler, _) = register(inner)?;
+        Some((code, scaler, 1))
+      },
+      _ => None,
+    }
+  }
+
+  let mut items = items.as_slice();
+  while let Some((code, scaler, len)) = register(items) {
+    items = &items[len..];
+
+    let scaler = match scaler {
+      Some(scaler) => scaler,
+      None => continue,
+    };
+
+    let scale = |n: f64| apply_scaler_f64(n, scaler);
+
+    obis_map.convert(&code, |value| match value {
+      Data::Integer(n) => Data::Float32(scale(n.into()) as f32),
+      Data::Unsigned(n) => Data::Float32(scale(n.into()) as f32),
+      Data::Long(n) => Data::Float32(scale(n.into()) as f32),
+      Data::DoubleLong(n) => Data::Float64(scale(n.into())),
+      Data::Long64(n) => Data::Float64(scale(n as f64)),
+      Data::Long64Unsigned(n) => Data::Float64(scale(n as f64)),
+      value => value,
+    });
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use dlms_cosem::Dlms;
+
+  use crate::Decoder;
+  use crate::fixture::{notification, notification_body, obis_map, COMPACT_ARRAY, FRAMES, KEY};
+
+  const TEMPERATURE: [u8; 6] = [0, 0, 96, 9, 0, 255];
+
+  #[test]
+  fn fixture_is_decodable() {
+    let mut decoder = Decoder::new(Dlms::new(KEY));
+    decoder.push(FRAMES);
+    decoder.decode().unwrap().unwrap();
+
+    let apdu = decrypt_apdu(decoder.last_apdu(), KEY).unwrap();
+    assert!(is_data_notification(&apdu));
+    assert!(is_decodable(&apdu));
+  }
+
+  #[test]
+  fn wrong_key_is_not_decodable() {
+    let mut decoder = Decoder::new(Dlms::new(KEY));
+    decoder.push(FRAMES);
+    decoder.decode().unwrap().unwrap();
+
+    let apdu = decrypt_apdu(decoder.last_apdu(), [0; 16]).unwrap();
+    assert!(!is_decodable(&apdu));
+  }
+
+  #[test]
+  fn scaler_range() {
+    for (scaler, decodable) in [(-9, true), (9, true), (-10, false), (10, false), (-128, false)] {
+      let apdu = notification(&[(TEMPERATURE, &[0x10, 0xff, 0x38], Some((scaler, 9)))]);
+      assert_eq!(is_decodable(&apdu), decodable, "scaler {}", scaler);
+    }
+  }
+
+  #[test]
+  fn nesting_depth() {
+    let nested = |depth: usize| notification_body(&[[0x02, 0x01].repeat(depth), vec![0x00]].concat());
+
+    assert!(is_decodable(&nested(MAX_DEPTH)));
+    assert!(!is_decodable(&nested(MAX_DEPTH + 1)));
+  }
+
+  #[test]
+  fn apply_scalers_signed() {
+    let apdu = notification(&[(TEMPERATURE, &[0x10, 0xff, 0x38], Some((-1, 9)))]);
+    let mut obis_map = obis_map(&apdu);
+    apply_scalers(&mut obis_map, &apdu).unwrap();
+
+    let code = ObisCode::new(0, 0, 96, 9, 0, 255);
+    assert_eq!(obis_map[&code].value(), &Data::Float32(-20.0));
+  }
+
+  #[test]
+  fn apply_scalers_mismatch() {
+    let apdu = notification(&[(TEMPERATURE, &[0x10, 0xff, 0x38], Some((-1, 9)))]);
+    let mut obis_map = obis_map(&apdu);
+    let expected = obis_map.clone();
+
+    let other = notification(&[([1, 0, 1, 8, 0, 255], &[0x10, 0xff, 0x38], Some((-1, 30)))]);
+    apply_scalers(&mut obis_map, &other).unwrap();
+    assert_eq!(obis_map, expected);
+
+    assert!(matches!(apply_scalers(&mut obis_map, &[0x0f, 0x00]), Err(Error::InvalidFormat)));
+    assert!(matches!(apply_scalers(&mut obis_map, &other[..(other.len() - 1)]), Err(Error::InvalidFormat)));
+    assert_eq!(obis_map, expected);
+  }
+
+  #[test]
+  fn compact_array() {
+    let apdu = notification_body(&[&[0x02, 0x02, 0x12, 0x00, 0x01][..], COMPACT_ARRAY].concat());
+    assert!(matches!(check_decodable(&apdu), Err(Error::CompactArray)));
+    assert!(!is_decodable(&apdu));
+
+    let mut obis_map = obis_map(&notification(&[]));
+    assert!(matches!(apply_scalers(&mut obis_map, &apdu), Err(Error::CompactArray)));
+
+    assert!(matches!(check_decodable(&notification_body(&[0x02, 0x01, 0xff])), Err(Error::InvalidFormat)));
+  }
+
+  #[test]
+  fn security_header() {
+    let header = |len: &[u8]| [[0xdb, 0x08].as_slice(), &[0; 8], len, &[0x21, 0x00, 0x00, 0x01, 0x02]].concat();
+
+    for len in [[0x81].as_slice(), &[0x82, 0x01, 0x00], &[0x7f]] {
+      let apdu = header(len);
+      assert_eq!(security_control(&apdu), Some(0x21), "length {:02x?}", len);
+      assert_eq!(invocation_counter(&apdu), Some(0x0102), "length {:02x?}", len);
+    }
+
+    assert_eq!(security_control(&[0x0f, 0x00]), None);
+    assert_eq!(invocation_counter(&header(&[0x82])[..13]), None);
+  }
+}
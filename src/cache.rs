@@ -0,0 +1,153 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::SystemTime;
+
+use dlms_cosem::{ObisCode, ObisMap, Register};
+
+use crate::{Clock, SmartMeter, SystemClock};
+
+/// A thread-safe cache of the latest value of every register.
+///
+/// Clones share the same cache, so one clone can be updated by a reader thread
+/// while others are queried.
+#[derive(Clone)]
+pub struct LatestCache {
+  registers: Arc<RwLock<BTreeMap<ObisCode, (Register, SystemTime)>>>,
+  clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl fmt::Debug for LatestCache {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("LatestCache")
+      .field("registers", &self.registers)
+      .finish_non_exhaustive()
+  }
+}
+
+impl Default for LatestCache {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl LatestCache {
+  pub fn new() -> Self {
+    Self::with_clock(SystemClock)
+  }
+
+  /// Create a cache which takes update timestamps from `clock`.
+  pub fn with_clock(clock: impl Clock + Send + Sync + 'static) -> Self {
+    Self { registers: Arc::default(), clock: Arc::new(clock) }
+  }
+
+  /// Create a cache which is updated from `smart_meter` on a background thread.
+  ///
+  /// Errors are skipped. The thread exits once the reader is exhausted, see [`Error::NoProgress`](crate::Error::NoProgress).
+  pub fn spawn<R: Read + Send + 'static>(smart_meter: SmartMeter<R>) -> Self {
+    let cache = Self::new();
+
+    {
+      let cache = cache.clone();
+      thread::spawn(move || {
+        for obis_map in smart_meter.flatten() {
+          cache.update(&obis_map);
+        }
+      });
+    }
+
+    cache
+  }
+
+  /// Store all registers of `obis_map`, replacing older values.
+  pub fn update(&self, obis_map: &ObisMap) {
+    let now = self.clock.system_time();
+    let mut registers = self.registers.write().unwrap_or_else(|err| err.into_inner());
+
+    for (code, reg) in obis_map.iter() {
+      registers.insert(code.clone(), (reg.clone(), now));
+    }
+  }
+
+  /// The latest register for the given code.
+  pub fn get(&self, code: &ObisCode) -> Option<Register> {
+    let registers = self.registers.read().unwrap_or_else(|err| err.into_inner());
+    registers.get(code).map(|(reg, _)| reg.clone())
+  }
+
+  /// The time the register for the given code was last updated.
+  pub fn last_updated(&self, code: &ObisCode) -> Option<SystemTime> {
+    let registers = self.registers.read().unwrap_or_else(|err| err.into_inner());
+    registers.get(code).map(|(_, time)| *time)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::io::Cursor;
+  use std::time::{Duration, Instant};
+
+  use dlms_cosem::Dlms;
+
+  use crate::MockClock;
+  use crate::value::as_f64;
+  use crate::fixture::{notification, obis_map, FRAMES, KEY};
+
+  const ENERGY: ObisCode = ObisCode::new(1, 0, 1, 8, 0, 255);
+  const POWER: ObisCode = ObisCode::new(1, 0, 1, 7, 0, 255);
+
+  #[test]
+  fn update_from_thread() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let clock = MockClock::new(start);
+    let cache = LatestCache::with_clock(clock.clone());
+
+    let both = obis_map(&notification(&[
+      ([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x01], None),
+      ([1, 0, 1, 7, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x02], None),
+    ]));
+    let power = obis_map(&notification(&[([1, 0, 1, 7, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x03], None)]));
+
+    let value = |code: &ObisCode| cache.get(code).and_then(|reg| as_f64(reg.value()));
+
+    assert_eq!(value(&ENERGY), None);
+    assert_eq!(cache.last_updated(&ENERGY), None);
+
+    {
+      let cache = cache.clone();
+      thread::spawn(move || cache.update(&both)).join().unwrap();
+    }
+
+    assert_eq!((value(&ENERGY), value(&POWER)), (Some(1.0), Some(2.0)));
+    assert_eq!((cache.last_updated(&ENERGY), cache.last_updated(&POWER)), (Some(start), Some(start)));
+
+    clock.advance(Duration::from_secs(1));
+
+    {
+      let cache = cache.clone();
+      thread::spawn(move || cache.update(&power)).join().unwrap();
+    }
+
+    // Only the updated code changes.
+    assert_eq!((value(&ENERGY), value(&POWER)), (Some(1.0), Some(3.0)));
+    assert_eq!(cache.last_updated(&ENERGY), Some(start));
+    assert_eq!(cache.last_updated(&POWER), Some(start + Duration::from_secs(1)));
+  }
+
+  #[test]
+  fn spawn() {
+    let cache = LatestCache::spawn(SmartMeter::new(Cursor::new(FRAMES), Dlms::new(KEY)));
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while cache.get(&ENERGY).is_none() && Instant::now() < deadline {
+      thread::sleep(Duration::from_millis(10));
+    }
+
+    assert!(cache.get(&ENERGY).is_some());
+    assert!(cache.last_updated(&ENERGY).is_some());
+  }
+}
@@ -0,0 +1,235 @@
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use dlms_cosem::Dlms;
+
+use crate::SmartMeter;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// A reader for a capture file, decompressing it if needed.
+#[derive(Debug)]
+pub enum CaptureFile {
+  Plain(BufReader<File>),
+  #[cfg(feature = "flate2")]
+  Gzip(flate2::bufread::MultiGzDecoder<BufReader<File>>),
+}
+
+impl CaptureFile {
+  /// Open a capture file.
+  ///
+  /// Files are detected as gzip-compressed by a `.gz` extension or the gzip magic bytes.
+  /// Decompression requires the `flate2` feature.
+  pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+    let path = path.as_ref();
+    let mut reader = BufReader::new(File::open(path)?);
+
+    let is_gzip = path.extension().is_some_and(|ext| ext == "gz") || reader.fill_buf()?.starts_with(&GZIP_MAGIC);
+    if !is_gzip {
+      return Ok(Self::Plain(reader))
+    }
+
+    #[cfg(feature = "flate2")]
+    return Ok(Self::Gzip(flate2::bufread::MultiGzDecoder::new(reader)));
+
+    #[cfg(not(feature = "flate2"))]
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reading gzip-compressed files requires the `flate2` feature"))
+  }
+}
+
+impl Read for CaptureFile {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    match self {
+      Self::Plain(reader) => reader.read(buf),
+      #[cfg(feature = "flate2")]
+      Self::Gzip(reader) => reader.read(buf),
+    }
+  }
+}
+
+impl SmartMeter<CaptureFile> {
+  /// Create a `SmartMeter` replaying a capture file, see [`CaptureFile::open`].
+  pub fn from_path(path: impl AsRef<Path>, dlms: Dlms) -> io::Result<Self> {
+    Ok(Self::new(CaptureFile::open(path)?, dlms))
+  }
+}
+
+/// A size-rotated capture of raw frames, e.g. for use with [`SmartMeter::on_frame`].
+///
+/// Each frame is written as a 4-byte big-endian length followed by the frame bytes.
+/// Frames are written to `path`; once it exceeds `max_size` bytes, it is renamed to `path.1`,
+/// existing `path.1` to `path.2` and so on, keeping at most `keep` rotated files.
+#[derive(Debug)]
+pub struct RollingCapture {
+  path: PathBuf,
+  max_size: u64,
+  keep: usize,
+  file: BufWriter<File>,
+  size: u64,
+}
+
+impl RollingCapture {
+  /// Open a capture at `path`, appending to an existing file.
+  pub fn new(path: impl Into<PathBuf>, max_size: u64, keep: usize) -> io::Result<Self> {
+    let path = path.into();
+    let file = File::options().create(true).append(true).open(&path)?;
+    let size = file.metadata()?.len();
+
+    Ok(Self { path, max_size, keep, file: BufWriter::new(file), size })
+  }
+
+  fn rotated_path(&self, n: usize) -> PathBuf {
+    let mut path = self.path.clone().into_os_string();
+    path.push(format!(".{}", n));
+    path.into()
+  }
+
+  fn rotate(&mut self) -> io::Result<()> {
+    self.file.flush()?;
+
+    if self.keep == 0 {
+      self.file = BufWriter::new(File::create(&self.path)?);
+    } else {
+      for n in (1..self.keep).rev() {
+        let from = self.rotated_path(n);
+        if from.exists() {
+          fs::rename(from, self.rotated_path(n + 1))?;
+        }
+      }
+      fs::rename(&self.path, self.rotated_path(1))?;
+      self.file = BufWriter::new(File::create(&self.path)?);
+    }
+
+    self.size = 0;
+    Ok(())
+  }
+
+  /// Write a frame, rotating files if needed. The frame is flushed immediately.
+  pub fn write_frame(&mut self, frame: &[u8]) -> io::Result<()> {
+    if self.size > 0 && self.size >= self.max_size {
+      self.rotate()?;
+    }
+
+    let len = u32::try_from(frame.len()).map_err(|_| io::Error::from(io::ErrorKind::InvalidInput))?;
+    self.file.write_all(&len.to_be_bytes())?;
+    self.file.write_all(frame)?;
+    self.file.flush()?;
+    self.size += 4 + u64::from(len);
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::env;
+  use std::process;
+
+  use crate::Error;
+  use crate::fixture::{FRAMES, KEY};
+
+  /// A path in the temporary directory which is unique to this test process.
+  fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("smart_meter_{}_{}", process::id(), name))
+  }
+
+  fn frame(byte: u8) -> Vec<u8> {
+    [[0, 0, 0, 8].as_slice(), &[byte; 8]].concat()
+  }
+
+  #[test]
+  fn rotate() {
+    let path = temp_path("rotate.bin");
+    let mut capture = RollingCapture::new(&path, 10, 2).unwrap();
+
+    for byte in 1..=4 {
+      capture.write_frame(&[byte; 8]).unwrap();
+    }
+
+    let rotated = (1..=3).map(|n| capture.rotated_path(n)).collect::<Vec<_>>();
+    assert_eq!(rotated[0], temp_path("rotate.bin.1"));
+
+    let contents = [&path, &rotated[0], &rotated[1]].map(|path| fs::read(path).unwrap());
+    let third_exists = rotated[2].exists();
+    for path in [&path, &rotated[0], &rotated[1]] {
+      fs::remove_file(path).unwrap();
+    }
+
+    assert_eq!(contents, [frame(4), frame(3), frame(2)]);
+    assert!(!third_exists);
+  }
+
+  #[test]
+  fn rotate_without_keeping() {
+    let path = temp_path("rotate_without_keeping.bin");
+    let mut capture = RollingCapture::new(&path, 10, 0).unwrap();
+
+    capture.write_frame(&[1; 8]).unwrap();
+    capture.write_frame(&[2; 8]).unwrap();
+
+    let contents = fs::read(&path).unwrap();
+    let rotated_exists = capture.rotated_path(1).exists();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(contents, frame(2));
+    assert!(!rotated_exists);
+  }
+
+  #[test]
+  fn append() {
+    let path = temp_path("append.bin");
+
+    RollingCapture::new(&path, 100, 1).unwrap().write_frame(&[1; 8]).unwrap();
+    RollingCapture::new(&path, 100, 1).unwrap().write_frame(&[2; 8]).unwrap();
+
+    let contents = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(contents, [frame(1), frame(2)].concat());
+  }
+
+  #[test]
+  fn from_path_plain() {
+    let path = temp_path("plain.bin");
+    fs::write(&path, FRAMES).unwrap();
+
+    let mut smart_meter = SmartMeter::from_path(&path, Dlms::new(KEY)).unwrap();
+    let reading = smart_meter.read_reading();
+    let end = smart_meter.read_reading();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(reading.unwrap().len(), 15);
+    assert!(matches!(end, Err(Error::NoProgress)));
+  }
+
+  #[cfg(feature = "flate2")]
+  #[test]
+  fn from_path_gzip_without_extension() {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(FRAMES).unwrap();
+
+    let path = temp_path("gzip.bin");
+    fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+    let mut smart_meter = SmartMeter::from_path(&path, Dlms::new(KEY)).unwrap();
+    let reading = smart_meter.read_reading();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(reading.unwrap().len(), 15);
+  }
+
+  #[cfg(not(feature = "flate2"))]
+  #[test]
+  fn from_path_gzip_unsupported() {
+    let path = temp_path("gzip.bin");
+    fs::write(&path, [GZIP_MAGIC.as_slice(), &[0x08, 0x00]].concat()).unwrap();
+
+    let result = SmartMeter::from_path(&path, Dlms::new(KEY));
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Unsupported);
+  }
+}
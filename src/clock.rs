@@ -0,0 +1,57 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+/// A source of time.
+pub trait Clock {
+  /// The current monotonic time.
+  fn now(&self) -> Instant;
+
+  /// The current wall-clock time.
+  fn system_time(&self) -> SystemTime;
+}
+
+/// The system clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+  fn now(&self) -> Instant {
+    Instant::now()
+  }
+
+  fn system_time(&self) -> SystemTime {
+    SystemTime::now()
+  }
+}
+
+/// A clock which only advances when told to, for deterministic tests.
+///
+/// Clones share the same time, so a clone can be advanced while another one is in use.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+  time: Arc<Mutex<(Instant, SystemTime)>>,
+}
+
+impl MockClock {
+  /// Create a clock starting at the given wall-clock time.
+  pub fn new(start: SystemTime) -> Self {
+    Self { time: Arc::new(Mutex::new((Instant::now(), start))) }
+  }
+
+  /// Advance the clock by the given duration.
+  pub fn advance(&self, duration: Duration) {
+    let mut time = self.time.lock().unwrap();
+    time.0 += duration;
+    time.1 += duration;
+  }
+}
+
+impl Clock for MockClock {
+  fn now(&self) -> Instant {
+    self.time.lock().unwrap().0
+  }
+
+  fn system_time(&self) -> SystemTime {
+    self.time.lock().unwrap().1
+  }
+}
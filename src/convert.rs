@@ -0,0 +1,85 @@
+//! Conversions for use with `ObisMap::convert`.
+
+use dlms_cosem::{Apdu, Data, DateTime, ObisCode, ObisMap};
+
+use crate::Error;
+use crate::apdu::catch_unimplemented;
+
+/// A conversion of a register value.
+pub type Conversion = fn(Data) -> Data;
+
+/// Convert an octet string into a date-time, returning other values unchanged.
+pub fn date_time(value: Data) -> Data {
+  match value {
+    Data::OctetString(bytes) => match DateTime::parse(&bytes) {
+      Ok((_, date_time)) => Data::DateTime(date_time),
+      Err(_) => Data::OctetString(bytes),
+    },
+    value => value,
+  }
+}
+
+/// Convert an octet string into a UTF-8 string, returning other values unchanged.
+pub fn utf8_string(value: Data) -> Data {
+  match value {
+    Data::OctetString(bytes) => match String::from_utf8(bytes) {
+      Ok(s) => Data::Utf8String(s),
+      Err(err) => Data::OctetString(err.into_bytes()),
+    },
+    value => value,
+  }
+}
+
+/// Decode the registers of a plain APDU, e.g. from [`decode_frame`](crate::decode_frame),
+/// and apply `conversions` in order, like [`SmartMeter::with_conversion`](crate::SmartMeter::with_conversion).
+///
+/// This allows reprocessing stored APDUs with different conversions. `apdu` is not modified.
+pub fn parse_with(apdu: &Apdu, conversions: &[(ObisCode, Conversion)]) -> Result<ObisMap, Error> {
+  let mut obis_map = match catch_unimplemented(|| ObisMap::parse(apdu)) {
+    Some(Ok((_, obis_map))) => obis_map,
+    _ => return Err(Error::InvalidFormat),
+  };
+
+  for (code, f) in conversions {
+    obis_map.convert(code, *f);
+  }
+
+  Ok(obis_map)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::decode_frame;
+  use crate::fixture::{FRAMES, KEY};
+
+  const CLOCK: ObisCode = ObisCode::new(0, 0, 1, 0, 0, 255);
+  const ENERGY: ObisCode = ObisCode::new(1, 0, 1, 8, 0, 255);
+
+  fn twice(value: Data) -> Data {
+    match value {
+      Data::Float64(n) => Data::Float64(n * 2.0),
+      value => value,
+    }
+  }
+
+  #[test]
+  fn parse_with_conversions() {
+    let (_, apdu) = decode_frame(FRAMES, KEY).unwrap();
+
+    let plain = parse_with(&apdu, &[]).unwrap();
+    assert!(matches!(plain[&CLOCK].value(), Data::OctetString(_)));
+
+    let converted = parse_with(&apdu, &[(CLOCK, date_time), (ENERGY, twice), (ENERGY, twice)]).unwrap();
+    assert_eq!(converted[&CLOCK].value(), &date_time(plain[&CLOCK].value().clone()));
+    assert!(matches!(converted[&CLOCK].value(), Data::DateTime(_)));
+
+    // Conversions are applied in order.
+    assert_eq!(plain[&ENERGY].value(), &Data::Float64(5308437.0));
+    assert_eq!(converted[&ENERGY].value(), &Data::Float64(4.0 * 5308437.0));
+
+    // The APDU can be decoded again.
+    assert_eq!(parse_with(&apdu, &[]).unwrap(), plain);
+  }
+}
@@ -0,0 +1,107 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use dlms_cosem::ObisMap;
+
+use crate::{format_value, Clock, Sink, SinkError, SystemClock};
+
+/// Quote a field if it contains a separator, a quote or a line break.
+fn quote(s: &str) -> String {
+  if s.contains([',', '"', '\n', '\r']) {
+    format!("\"{}\"", s.replace('"', "\"\""))
+  } else {
+    s.to_owned()
+  }
+}
+
+/// Writes readings as CSV with one row per register.
+///
+/// Rows have the columns `timestamp,code,value,unit`, where `timestamp` is the time the reading
+/// was written in seconds since the Unix epoch. No header is written, so appending to an existing
+/// file keeps it valid. Rows are flushed after each reading.
+pub struct CsvWriter<W> {
+  writer: W,
+  clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl<W: fmt::Debug> fmt::Debug for CsvWriter<W> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CsvWriter")
+      .field("writer", &self.writer)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<W: Write> CsvWriter<W> {
+  pub fn new(writer: W) -> Self {
+    Self::with_clock(writer, SystemClock)
+  }
+
+  /// Create a writer which takes timestamps from `clock`.
+  pub fn with_clock(writer: W, clock: impl Clock + Send + Sync + 'static) -> Self {
+    Self { writer, clock: Arc::new(clock) }
+  }
+
+  /// Write one row for each register of a reading.
+  pub fn write(&mut self, obis_map: &ObisMap) -> io::Result<()> {
+    let timestamp = self.clock.system_time().duration_since(UNIX_EPOCH).unwrap_or_default();
+    let timestamp = format!("{:.3}", timestamp.as_secs_f64());
+
+    let mut rows = String::new();
+    for (code, reg) in obis_map.iter() {
+      let value = format_value(reg.value());
+      let unit = reg.unit().and_then(|u| u.as_str()).unwrap_or("");
+      rows.push_str(&format!("{},{},{},{}\n", timestamp, code, quote(&value), quote(unit)));
+    }
+
+    self.writer.write_all(rows.as_bytes())?;
+    self.writer.flush()
+  }
+
+  /// Get back the underlying writer.
+  pub fn into_inner(self) -> W {
+    self.writer
+  }
+}
+
+impl<W: Write> Sink for CsvWriter<W> {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    Ok(self.write(reading)?)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::time::{Duration, SystemTime};
+
+  use crate::MockClock;
+  use crate::fixture::{notification, obis_map};
+
+  #[test]
+  fn quote_fields() {
+    assert_eq!(quote("Wh"), "Wh");
+    assert_eq!(quote("a,b"), "\"a,b\"");
+    assert_eq!(quote("say \"hi\""), "\"say \"\"hi\"\"\"");
+  }
+
+  #[test]
+  fn write_rows() {
+    let obis_map = obis_map(&notification(&[
+      ([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x04, 0xd2], Some((0, 30))),
+      ([0, 0, 96, 1, 0, 255], &[0x09, 0x02, 0x01, 0x02], None),
+    ]));
+
+    let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_millis(1500));
+    let mut writer = CsvWriter::with_clock(Vec::new(), clock);
+    writer.write(&obis_map).unwrap();
+
+    let csv = String::from_utf8(writer.into_inner()).unwrap();
+    let mut rows = csv.lines().collect::<Vec<_>>();
+    rows.sort();
+    assert_eq!(rows, ["1.500,0-0:96.1.0*255,\"OctetString([1, 2])\",", "1.500,1-0:1.8.0*255,1234,Wh"]);
+  }
+}
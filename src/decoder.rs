@@ -0,0 +1,1053 @@
+use std::fmt::Write;
+use std::io::{self, Read};
+use std::num::NonZeroUsize;
+
+use dlms_cosem::{Apdu, Error as DlmsError, Dlms, ObisMap};
+use mbusparse::{Error as MbusError, Telegram};
+
+use crate::{decrypt_apdu, Error, Stats};
+use crate::apdu::{assemble_into, catch_unimplemented, check_decodable, has_valid_header, invocation_counter, security_control};
+
+/// Maximum number of user data bytes in an M-Bus long frame, excluding control information.
+const MAX_USER_DATA_LEN: usize = 252;
+
+/// Default number of bytes which may be skipped before assuming a baud rate mismatch.
+pub const DEFAULT_BAUD_MISMATCH_THRESHOLD: usize = 4096;
+
+/// A custom M-Bus checksum verifier.
+///
+/// It is called with the checksummed bytes of a frame, i.e. everything between the start
+/// and the checksum field, and the received checksum and returns whether the frame is valid.
+pub type ChecksumVerifier = fn(data: &[u8], checksum: u8) -> bool;
+
+/// A transport header parser for readings received through a bridge.
+///
+/// It is called with the buffered bytes wherever a frame start is expected but missing and
+/// returns the length of the header at the start of `bytes`, or `None` if there is none.
+/// A length longer than `bytes` means more bytes are needed, the parser is called again
+/// once they were pushed.
+///
+/// Common bridges:
+/// - Raw TCP bridges like `ser2net` or ESP-Link forward bytes unchanged and need no parser.
+/// - Bridges prefixing each frame with its length as a 16-bit big-endian integer can be handled with
+///   `|bytes| match bytes { [_, _, 0x68, ..] => Some(2), [_] | [_, _] => Some(3), _ => None }`, or more reliably by wrapping the reader
+///   in a [`LengthPrefixedTransport`](crate::LengthPrefixedTransport), which also supports other lengths and byte orders.
+/// - RFC 2217 (Telnet) bridges escape bytes within frames, which cannot be undone by skipping a
+///   header, so they should be configured for raw mode instead.
+pub type TransportHeader = fn(bytes: &[u8]) -> Option<usize>;
+
+/// The quality of a decoded reading.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct Quality {
+  /// Whether all frames passed the standard M-Bus checksum,
+  /// i.e. none had to be accepted by a custom [`ChecksumVerifier`].
+  pub checksum_ok: bool,
+  /// The invocation counter of encrypted or authenticated APDUs.
+  ///
+  /// With a [`replay_window`](Decoder::replay_window), readings reusing a counter are rejected
+  /// with [`Error::Replayed`], so every counter is only reported once.
+  pub invocation_counter: Option<u32>,
+}
+
+impl Default for Quality {
+  fn default() -> Self {
+    Self { checksum_ok: true, invocation_counter: None }
+  }
+}
+
+/// A snapshot of the frame assembly state of a [`Decoder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct DebugState {
+  /// The number of telegrams currently needed to assemble the next APDU.
+  pub telegrams_needed: usize,
+  /// The number of complete telegrams in the buffer.
+  pub buffered_telegrams: usize,
+  /// The number of bytes in the buffer.
+  pub bytes_pending: usize,
+  /// The number of bytes requested by the last parse attempt.
+  pub bytes_needed: usize,
+}
+
+/// The number of bytes needed to complete the frame at the start of `buffer`.
+///
+/// The parser only reports the bytes needed for its next step, e.g. one byte at a time
+/// while parsing the header, so use the frame length once it is known instead.
+fn bytes_needed(buffer: &[u8], hint: Option<NonZeroUsize>) -> usize {
+  let frame_len = match buffer {
+    [0x68, len, ..] => usize::from(*len) + 6,
+    [0x68] => 4,
+    [0x10, ..] => 5,
+    _ => 0,
+  };
+
+  hint.map_or(1, |n| n.get()).max(frame_len.saturating_sub(buffer.len()))
+}
+
+/// A sans-io decoder which turns pushed bytes into readings.
+#[derive(Debug)]
+pub struct Decoder {
+  dlms: Dlms,
+  broadcast_dlms: Option<Dlms>,
+  buffer: Vec<u8>,
+  max_buffer: Option<usize>,
+  bytes_needed: usize,
+  telegrams_needed: usize,
+  skipped_bytes: usize,
+  baud_mismatch_threshold: Option<usize>,
+  last_apdu: Vec<u8>,
+  checksum: Option<ChecksumVerifier>,
+  transport_header: Option<TransportHeader>,
+  lenient_frames: Vec<usize>,
+  lenient_start: bool,
+  capture_failures: bool,
+  skip_decryption_failures: bool,
+  last_quality: Quality,
+  replay_window: Option<u32>,
+  invocation_counters: Vec<u32>,
+  telegrams_hint: usize,
+  last_frames: Vec<u8>,
+  last_frame_lens: Vec<usize>,
+  stats: Stats,
+}
+
+impl Decoder {
+  pub fn new(dlms: Dlms) -> Self {
+    Self::with_capacity(dlms, 0)
+  }
+
+  /// Create a decoder with an internal buffer pre-allocated for `capacity` bytes.
+  ///
+  /// An M-Bus long frame is at most 261 bytes long and readings usually span up to two frames,
+  /// so a capacity of 522 bytes avoids reallocations for most meters.
+  ///
+  /// Decryption and parsing are done by `dlms_cosem` on the complete APDU, so a reading cannot be
+  /// decoded incrementally. Peak memory is roughly three times the APDU size: the buffered frames,
+  /// the reassembled APDU and its decrypted copy, plus the decoded registers.
+  pub fn with_capacity(dlms: Dlms, capacity: usize) -> Self {
+    Decoder {
+      dlms,
+      broadcast_dlms: None,
+      buffer: Vec::with_capacity(capacity),
+      max_buffer: None,
+      bytes_needed: 0,
+      telegrams_needed: 1,
+      skipped_bytes: 0,
+      baud_mismatch_threshold: Some(DEFAULT_BAUD_MISMATCH_THRESHOLD),
+      last_apdu: Vec::new(),
+      checksum: None,
+      transport_header: None,
+      lenient_frames: Vec::new(),
+      lenient_start: false,
+      capture_failures: false,
+      skip_decryption_failures: false,
+      last_quality: Quality::default(),
+      replay_window: None,
+      invocation_counters: Vec::new(),
+      telegrams_hint: 1,
+      last_frames: Vec::new(),
+      last_frame_lens: Vec::new(),
+      stats: Stats::default(),
+    }
+  }
+
+  /// Create a decoder with an internal buffer which is allocated once for `limit` bytes and never grows.
+  ///
+  /// Readings whose frames do not fit are dropped with [`Error::FrameTooLarge`]. An M-Bus long frame
+  /// is at most 261 bytes long, so the limit should be at least 261 bytes times the number of frames
+  /// per reading, i.e. 522 bytes for most meters. [`push`](Self::push) only accepts as many bytes as fit.
+  ///
+  /// `dlms_cosem` still allocates while decrypting and decoding, see [`with_capacity`](Self::with_capacity).
+  ///
+  /// The buffer is still a heap-allocated `Vec`. There is no constructor taking a caller-provided
+  /// `&mut [u8]`, since it would need a lifetime on `Decoder` and [`SmartMeter`](crate::SmartMeter),
+  /// and decoding cannot avoid the heap anyway as long as `dlms_cosem` allocates.
+  pub fn with_capacity_limit(dlms: Dlms, limit: usize) -> Self {
+    let mut decoder = Self::with_capacity(dlms, limit);
+    decoder.max_buffer = Some(limit);
+    decoder
+  }
+
+  /// Set the number of bytes which may be skipped without decoding a reading
+  /// before [`Error::LikelyBaudMismatch`] is returned, or `None` to disable the check.
+  pub fn baud_mismatch_threshold(mut self, threshold: Option<usize>) -> Self {
+    self.baud_mismatch_threshold = threshold;
+    self
+  }
+
+  /// Verify M-Bus checksums using a custom verifier, for meters which use a nonstandard checksum.
+  ///
+  /// Frames which fail the standard check are only accepted if the verifier accepts them.
+  pub fn with_checksum(mut self, verifier: ChecksumVerifier) -> Self {
+    self.checksum = Some(verifier);
+    self
+  }
+
+  /// Strip transport headers added by a bridge before frames, see [`TransportHeader`].
+  ///
+  /// By default, frames are expected to start with one of the standard M-Bus start characters
+  /// and any other bytes are skipped.
+  pub fn with_transport_header(mut self, parser: TransportHeader) -> Self {
+    self.transport_header = Some(parser);
+    self
+  }
+
+  /// Restore the start character of long frames where it is missing, e.g. in damaged captures.
+  ///
+  /// Disabled by default. When enabled, bytes which look like the rest of a long frame header,
+  /// i.e. two equal length bytes followed by the second start character, are assumed to be one.
+  /// This is only meant for recovering captures, on random input it can produce garbage frames.
+  pub fn lenient_start(mut self, lenient: bool) -> Self {
+    self.lenient_start = lenient;
+    self
+  }
+
+  /// Include a copy of the raw, still encrypted APDU in [`Error::DecryptionFailed`],
+  /// e.g. for analyzing failures offline.
+  ///
+  /// Disabled by default to avoid the allocation.
+  pub fn capture_failures(mut self, capture: bool) -> Self {
+    self.capture_failures = capture;
+    self
+  }
+
+  /// Continue with the next reading instead of returning [`Error::DecryptionFailed`]
+  /// for frames which fail to decrypt. These frames are dropped in either case.
+  ///
+  /// Disabled by default. Skipped failures are still counted in [`Stats::decryption_failures`].
+  pub fn skip_decryption_failures(mut self, skip: bool) -> Self {
+    self.skip_decryption_failures = skip;
+    self
+  }
+
+  /// Hint the expected APDU size in bytes, so internal buffers are pre-allocated accordingly.
+  pub fn payload_size_hint(mut self, size: usize) -> Self {
+    self.telegrams_hint = size.div_ceil(MAX_USER_DATA_LEN).max(1);
+    self.last_apdu.reserve(size);
+    self
+  }
+
+  /// Reject readings whose invocation counter was already seen or is more than `window` below
+  /// the highest one seen so far with [`Error::Replayed`].
+  ///
+  /// Replay rejection is disabled by default. A window of 0 only accepts strictly increasing
+  /// counters, larger windows tolerate minor reordering, e.g. on lossy links.
+  pub fn replay_window(mut self, window: u32) -> Self {
+    self.replay_window = Some(window);
+    self
+  }
+
+  /// Decrypt APDUs using the broadcast key set, i.e. with bit 6 of the security control byte set,
+  /// with a separate DLMS context.
+  ///
+  /// Without a broadcast context, such APDUs are rejected with [`Error::MissingBroadcastKey`].
+  pub fn with_broadcast_key(mut self, dlms: Dlms) -> Self {
+    self.broadcast_dlms = Some(dlms);
+    self
+  }
+
+  fn check_replay(&mut self) -> Result<(), Error> {
+    let (window, counter) = match (self.replay_window, invocation_counter(&self.last_apdu)) {
+      (Some(window), Some(counter)) => (window, counter),
+      _ => return Ok(()),
+    };
+
+    if let Some(&max) = self.invocation_counters.iter().max() {
+      if self.invocation_counters.contains(&counter) || counter < max.saturating_sub(window) {
+        return Err(Error::Replayed)
+      }
+    }
+
+    self.invocation_counters.push(counter);
+    let min = self.invocation_counters.iter().max().map_or(0, |max| max.saturating_sub(window));
+    self.invocation_counters.retain(|&c| c >= min);
+
+    Ok(())
+  }
+
+  /// Replace the DLMS context, e.g. after the key was changed.
+  ///
+  /// Buffered bytes are kept and will be decrypted using the new context. Invocation counters
+  /// seen so far are forgotten, since a new key usually restarts them, see [`replay_window`](Self::replay_window).
+  pub fn set_dlms(&mut self, dlms: Dlms) {
+    self.dlms = dlms;
+    self.invocation_counters.clear();
+    self.last_quality = Quality::default();
+  }
+
+  /// Discard all buffered bytes and any partially assembled frames.
+  pub fn clear(&mut self) {
+    self.buffer.clear();
+    self.lenient_frames.clear();
+    self.bytes_needed = 0;
+    self.telegrams_needed = 1;
+    self.skipped_bytes = 0;
+  }
+
+  fn drain(&mut self, n: usize) {
+    self.buffer.drain(0..n);
+    self.lenient_frames.retain_mut(|offset| {
+      *offset = offset.wrapping_sub(n);
+      *offset < self.buffer.len()
+    });
+  }
+
+  fn skip(&mut self, n: usize) -> Result<(), Error> {
+    self.drain(n);
+    self.skipped_bytes += n;
+
+    if let Some(threshold) = self.baud_mismatch_threshold {
+      if self.skipped_bytes > threshold {
+        self.skipped_bytes = 0;
+        return Err(Error::LikelyBaudMismatch)
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Check the frame at the given offset using the custom checksum verifier and,
+  /// if it is accepted, replace its checksum with the standard one.
+  fn accept_checksum(&mut self, offset: usize) -> bool {
+    let verifier = match self.checksum {
+      Some(verifier) => verifier,
+      None => return false,
+    };
+
+    let frame = &mut self.buffer[offset..];
+    let (start, end) = match frame {
+      [0x68, len, ..] => (4, 4 + usize::from(*len)),
+      [0x10, ..] => (1, 3),
+      _ => return false,
+    };
+
+    let (data, checksum) = match (frame.get(start..end), frame.get(end)) {
+      (Some(data), Some(&checksum)) => (data, checksum),
+      _ => return false,
+    };
+
+    if !verifier(data, checksum) {
+      return false
+    }
+
+    frame[end] = data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+    self.lenient_frames.push(offset);
+    true
+  }
+
+  /// Insert a missing long frame start character at the given offset, see [`lenient_start`](Self::lenient_start).
+  ///
+  /// Returns `None` if more bytes are needed to decide. Nothing is inserted if the buffer is full,
+  /// see [`with_capacity_limit`](Self::with_capacity_limit).
+  fn restore_start(&mut self, offset: usize) -> Option<bool> {
+    if !self.lenient_start || self.remaining_capacity() == 0 {
+      return Some(false)
+    }
+
+    match self.buffer[offset..] {
+      [len] if len >= 3 => None,
+      [len1, len2] if len1 == len2 && len1 >= 3 => None,
+      [len1, len2, 0x68, ..] if len1 == len2 && len1 >= 3 => {
+        self.buffer.insert(offset, 0x68);
+        for frame in self.lenient_frames.iter_mut().filter(|frame| **frame >= offset) {
+          *frame += 1;
+        }
+        Some(true)
+      },
+      _ => Some(false),
+    }
+  }
+
+  /// Remove a transport header at the given offset, see [`with_transport_header`](Self::with_transport_header).
+  ///
+  /// Returns `None` and sets `bytes_needed` if the header is not fully buffered yet. A header which
+  /// does not fit into the buffer is treated as no header, see [`with_capacity_limit`](Self::with_capacity_limit).
+  fn strip_transport_header(&mut self, offset: usize) -> Option<bool> {
+    let len = match self.transport_header.and_then(|parser| parser(&self.buffer[offset..])) {
+      Some(len) if len > 0 => len,
+      _ => return Some(false),
+    };
+
+    let available = self.buffer.len() - offset;
+    if len > available {
+      if len - available > self.remaining_capacity() {
+        return Some(false)
+      }
+
+      self.bytes_needed = len - available;
+      return None
+    }
+
+    self.buffer.drain(offset..(offset + len));
+    for frame in self.lenient_frames.iter_mut().filter(|frame| **frame >= offset) {
+      *frame -= len;
+    }
+    Some(true)
+  }
+
+  /// Skip to the next byte which could start a telegram.
+  ///
+  /// This is equivalent to skipping one byte at a time, since parsing fails
+  /// immediately on any other byte. With [`lenient_start`](Self::lenient_start),
+  /// the rest of a long frame header missing its start character also counts.
+  fn resync(&mut self) -> Result<(), Error> {
+    let lenient_start = self.lenient_start;
+    let is_start = |bytes: &[u8]| match *bytes {
+      [0x68 | 0x10 | 0xe5, ..] => true,
+      [len] | [len, _] | [len, _, 0x68, ..] if lenient_start && len >= 3 => bytes.get(1).is_none_or(|&len2| len2 == len),
+      _ => false,
+    };
+
+    let n = (1..self.buffer.len())
+      .find(|&i| is_start(&self.buffer[i..]))
+      .unwrap_or(self.buffer.len());
+    self.skip(n)
+  }
+
+  /// The number of bytes which can be buffered before reaching the limit, see [`with_capacity_limit`](Self::with_capacity_limit).
+  fn remaining_capacity(&self) -> usize {
+    self.max_buffer.map_or(usize::MAX, |max| max.saturating_sub(self.buffer.len()))
+  }
+
+  /// Append bytes to the internal buffer.
+  ///
+  /// Returns the number of bytes appended, which is less than `bytes.len()` only if the buffer is limited,
+  /// see [`with_capacity_limit`](Self::with_capacity_limit). The rest should be pushed again after decoding.
+  pub fn push(&mut self, bytes: &[u8]) -> usize {
+    let bytes = &bytes[..bytes.len().min(self.remaining_capacity())];
+    self.buffer.extend_from_slice(bytes);
+    self.stats.bytes_read += bytes.len() as u64;
+    bytes.len()
+  }
+
+  /// Append the remaining bytes of a [`bytes::Buf`] to the internal buffer.
+  ///
+  /// Returns the number of bytes appended, see [`push`](Self::push).
+  #[cfg(feature = "bytes")]
+  pub fn push_buf(&mut self, mut buf: impl bytes::Buf) -> usize {
+    let len = buf.remaining().min(self.remaining_capacity());
+    self.buffer.reserve(len);
+    self.stats.bytes_read += len as u64;
+
+    let mut remaining = len;
+    while remaining > 0 {
+      let chunk = &buf.chunk()[..remaining.min(buf.chunk().len())];
+      let chunk_len = chunk.len();
+      self.buffer.extend_from_slice(chunk);
+      buf.advance(chunk_len);
+      remaining -= chunk_len;
+    }
+
+    len
+  }
+
+  /// The number of bytes which should be pushed before calling [`decode`](Self::decode) again.
+  pub fn bytes_needed(&self) -> usize {
+    self.bytes_needed
+  }
+
+  /// The raw, still encrypted APDU of the last decoded reading.
+  ///
+  /// Use [`decrypt_apdu`](crate::decrypt_apdu) to get the plain APDU bytes.
+  pub fn last_apdu(&self) -> &[u8] {
+    &self.last_apdu
+  }
+
+  /// The number of M-Bus telegrams the last decoded reading was assembled from.
+  pub fn last_telegram_count(&self) -> usize {
+    self.last_frame_lens.len()
+  }
+
+  /// The raw M-Bus telegrams of the last decoded reading.
+  pub fn last_frames(&self) -> impl Iterator<Item = &[u8]> {
+    let mut frames = self.last_frames.as_slice();
+
+    self.last_frame_lens.iter().map(move |&len| {
+      let (frame, rest) = frames.split_at(len);
+      frames = rest;
+      frame
+    })
+  }
+
+  /// The quality of the last decoded reading.
+  pub fn last_quality(&self) -> Quality {
+    self.last_quality
+  }
+
+  /// Statistics collected so far.
+  pub fn stats(&self) -> &Stats {
+    &self.stats
+  }
+
+  /// The buffered bytes not yet consumed, as space-separated lowercase hex.
+  pub fn buffer_hex(&self) -> String {
+    let mut hex = String::with_capacity(self.buffer.len() * 3);
+
+    for (i, byte) in self.buffer.iter().enumerate() {
+      if i > 0 {
+        hex.push(' ');
+      }
+      write!(hex, "{:02x}", byte).unwrap();
+    }
+
+    hex
+  }
+
+  /// Get a snapshot of the current frame assembly state.
+  pub fn debug_state(&self) -> DebugState {
+    let mut buffered_telegrams = 0;
+    let mut buffer = self.buffer.as_slice();
+    while let Ok((next_buffer, _)) = Telegram::parse(buffer) {
+      buffered_telegrams += 1;
+      buffer = next_buffer;
+    }
+
+    DebugState {
+      telegrams_needed: self.telegrams_needed,
+      buffered_telegrams,
+      bytes_pending: self.buffer.len(),
+      bytes_needed: self.bytes_needed,
+    }
+  }
+
+  /// Read exactly the number of bytes needed from the given reader.
+  pub(crate) fn fill_from<R: Read>(&mut self, reader: &mut R) -> io::Result<usize> {
+    let len = self.buffer.len();
+    let bytes_needed = self.bytes_needed.min(self.remaining_capacity());
+    // Make room for all bytes up front, so they can be read in a single call.
+    self.buffer.reserve(bytes_needed);
+    let result = reader.take(bytes_needed as u64).read_to_end(&mut self.buffer);
+    self.stats.bytes_read += (self.buffer.len() - len) as u64;
+    result
+  }
+
+  /// Discard the next frame from the buffered bytes without decrypting it.
+  ///
+  /// Returns `Ok(false)` if more bytes are needed, see [`bytes_needed`](Self::bytes_needed).
+  pub fn skip_frame(&mut self) -> Result<bool, Error> {
+    self.telegrams_needed = 1;
+
+    loop {
+      match Telegram::parse(&self.buffer) {
+        Ok((next_buffer, _)) => {
+          let telegram_len = self.buffer.len() - next_buffer.len();
+          self.drain(telegram_len);
+          self.bytes_needed = 0;
+          self.stats.skipped_frames += 1;
+          return Ok(true)
+        },
+        Err(MbusError::Incomplete(n)) => {
+          self.bytes_needed = bytes_needed(&self.buffer, n);
+          return Ok(false)
+        },
+        Err(MbusError::ChecksumMismatch) => {
+          if !self.accept_checksum(0) {
+            self.resync()?;
+          }
+        },
+        Err(MbusError::InvalidStartCharacter) => match self.restore_start(0) {
+          Some(true) => (),
+          Some(false) => self.resync()?,
+          None => {
+            self.bytes_needed = 3 - self.buffer.len();
+            return Ok(false)
+          },
+        },
+        Err(MbusError::InvalidFormat) => {
+          self.resync()?;
+        },
+      }
+    }
+  }
+
+  /// Try decoding the next reading from the buffered bytes.
+  ///
+  /// Returns `Ok(None)` if more bytes are needed, see [`bytes_needed`](Self::bytes_needed).
+  ///
+  /// A wrong key produces garbage plain text, which `dlms_cosem` may panic on. Since [`Dlms`] does
+  /// not expose the key, the plain text cannot be checked beforehand, so these panics are caught and
+  /// returned as [`Error::DecryptionFailed`]. The panic hook still runs though, printing the panic
+  /// message, and with `panic = "abort"` the process aborts. Check the key with
+  /// [`SmartMeter::validate_key`](crate::SmartMeter::validate_key) to avoid this.
+  ///
+  /// `dlms_cosem` cannot decode compact arrays either. Unencrypted readings containing them fail with
+  /// [`Error::CompactArray`], while encrypted ones cannot be told apart from a wrong key and fail with
+  /// [`Error::DecryptionFailed`]. Use [`decode_frame`] or [`decrypt_apdu`] with [`DataTree`](crate::DataTree) instead.
+  pub fn decode(&mut self) -> Result<Option<ObisMap>, Error> {
+    let obis_map = self.decode_with(|dlms, broadcast_dlms, telegrams, apdu| {
+      assemble_into(telegrams, apdu)?;
+
+      // Consume the frames so the next reading can be decoded.
+      if let Err(Error::CompactArray) = check_decodable(apdu) {
+        return Ok(Err(Error::CompactArray))
+      }
+
+      if !has_valid_header(apdu) {
+        return Err(DlmsError::InvalidFormat)
+      }
+
+      let dlms = match security_control(apdu) {
+        Some(control) if control & 0x40 != 0 => match broadcast_dlms {
+          Some(broadcast_dlms) => broadcast_dlms,
+          None => return Ok(Err(Error::MissingBroadcastKey)),
+        },
+        _ => dlms,
+      };
+
+      // The header is valid, so a panic means the plain text is garbage, most likely due to a wrong key.
+      let (_, obis_map) = catch_unimplemented(|| dlms.decrypt(telegrams)).unwrap_or(Err(DlmsError::DecryptionFailed))?;
+      Ok(Ok(obis_map))
+    })?;
+
+    let obis_map = match obis_map {
+      Some(Ok(obis_map)) => Some(obis_map),
+      Some(Err(err)) => return Err(err),
+      None => None,
+    };
+
+    if obis_map.is_some() {
+      self.check_replay()?;
+    }
+
+    Ok(obis_map)
+  }
+
+  /// Try reassembling the next raw APDU from the buffered bytes without decrypting it.
+  ///
+  /// Returns `Ok(None)` if more bytes are needed, see [`bytes_needed`](Self::bytes_needed).
+  pub(crate) fn decode_apdu(&mut self) -> Result<Option<Vec<u8>>, Error> {
+    self.decode_with(|_, _, telegrams, apdu| {
+      assemble_into(telegrams, apdu)?;
+      Ok(apdu.clone())
+    })
+  }
+
+  fn decode_with<T>(
+    &mut self,
+    mut f: impl FnMut(&Dlms, Option<&Dlms>, &[Telegram<'_>], &mut Vec<u8>) -> Result<T, DlmsError>,
+  ) -> Result<Option<T>, Error> {
+    'outer: loop {
+      let mut telegrams = Vec::with_capacity(self.telegrams_needed.max(self.telegrams_hint));
+
+      let mut buffer = self.buffer.as_slice();
+      let mut telegram_lens = Vec::with_capacity(telegrams.capacity());
+      let mut telegram_1_len = 0;
+      let mut telegrams_len = 0;
+
+      for i in 0..self.telegrams_needed {
+        match Telegram::parse(buffer) {
+          Ok((next_buffer, telegram)) => {
+            let telegram_len = buffer.len() - next_buffer.len();
+            telegram_lens.push(telegram_len);
+            if i == 0 {
+              telegram_1_len = telegram_len;
+            }
+            telegrams_len += telegram_len;
+            buffer = next_buffer;
+            telegrams.push(telegram);
+          },
+          Err(MbusError::Incomplete(n)) => {
+            self.bytes_needed = bytes_needed(buffer, n);
+
+            if self.max_buffer.is_some_and(|max| self.buffer.len() + self.bytes_needed > max) {
+              self.telegrams_needed = 1;
+              self.bytes_needed = 0;
+              if telegram_1_len > 0 {
+                self.skip(telegram_1_len)?;
+              } else {
+                self.resync()?;
+              }
+              return Err(Error::FrameTooLarge)
+            }
+
+            return Ok(None)
+          },
+          Err(MbusError::InvalidStartCharacter) => {
+            let offset = self.buffer.len() - buffer.len();
+            match self.restore_start(offset) {
+              Some(true) => (),
+              Some(false) => match self.strip_transport_header(offset) {
+                Some(true) => (),
+                Some(false) => self.resync()?,
+                None => return Ok(None),
+              },
+              None => {
+                self.bytes_needed = 3 - (self.buffer.len() - offset);
+                return Ok(None)
+              },
+            }
+            continue 'outer
+          }
+          Err(MbusError::ChecksumMismatch) if self.checksum.is_some() => {
+            let offset = self.buffer.len() - buffer.len();
+            if !self.accept_checksum(offset) {
+              self.resync()?;
+            }
+            continue 'outer
+          },
+          Err(MbusError::InvalidFormat | MbusError::ChecksumMismatch) => {
+            // Input is invalid but not incomplete,
+            // so try advancing the buffer.
+            self.resync()?;
+            continue 'outer
+          },
+        }
+      }
+      self.bytes_needed = 0;
+
+      match f(&self.dlms, self.broadcast_dlms.as_ref(), &telegrams, &mut self.last_apdu) {
+        Ok(value) => {
+          self.last_quality = Quality {
+            checksum_ok: !self.lenient_frames.iter().any(|&offset| offset < telegrams_len),
+            invocation_counter: invocation_counter(&self.last_apdu),
+          };
+          self.last_frames.clear();
+          self.last_frames.extend_from_slice(&self.buffer[..telegrams_len]);
+          self.last_frame_lens = telegram_lens;
+          self.drain(telegrams_len);
+          self.stats.useful_bytes += telegrams_len as u64;
+          self.telegrams_needed = 1;
+          self.skipped_bytes = 0;
+          return Ok(Some(value))
+        },
+        Err(DlmsError::Incomplete(n)) => {
+          self.telegrams_needed += n.map(|t| t.get()).unwrap_or(1);
+        },
+        Err(DlmsError::InvalidFormat | DlmsError::ChecksumMismatch) => {
+          // Other error, continue with next telegram.
+          self.telegrams_needed = 1;
+          self.skip(telegram_1_len)?;
+          continue
+        },
+        Err(DlmsError::DecryptionFailed) => {
+          self.telegrams_needed = 1;
+          self.stats.decryption_failures += 1;
+          // Drop the frames either way, otherwise the same error would be returned forever.
+          self.drain(telegrams_len);
+
+          if self.skip_decryption_failures {
+            continue
+          }
+
+          let apdu = self.capture_failures.then(|| self.last_apdu.clone());
+          return Err(Error::DecryptionFailed(apdu))
+        },
+      }
+    }
+  }
+}
+
+/// Decode the first reading from a byte slice, without any reader.
+///
+/// Returns the number of bytes consumed, including any skipped leading bytes, and the plain APDU.
+/// Takes the raw key, like [`decrypt_apdu`](crate::decrypt_apdu), since [`Dlms`] does not expose it.
+/// Fails with [`io::ErrorKind::UnexpectedEof`] if `bytes` does not contain a complete reading.
+pub fn decode_frame(bytes: &[u8], key: [u8; 16]) -> Result<(usize, Apdu), Error> {
+  let mut decoder = Decoder::new(Dlms::new(key));
+  decoder.push(bytes);
+
+  let apdu = match decoder.decode_apdu()? {
+    Some(apdu) => decrypt_apdu(&apdu, key)?,
+    None => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+  };
+
+  check_decodable(&apdu)?;
+
+  match Apdu::parse(&apdu) {
+    Ok((_, apdu)) => Ok((bytes.len() - decoder.buffer.len(), apdu)),
+    Err(_) => Err(Error::InvalidFormat),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use dlms_cosem::{Data, ObisCode};
+
+  use crate::DataTree;
+  use crate::fixture::{frames_with_key, notification, notification_body, patch_frames, plain_frame, COMPACT_ARRAY, FRAMES, FRAME_1_LEN, KEY, SECURITY_CONTROL};
+
+  fn decoder(bytes: &[u8]) -> Decoder {
+    let mut decoder = Decoder::new(Dlms::new(KEY));
+    decoder.push(bytes);
+    decoder
+  }
+
+  #[test]
+  fn decode() {
+    let mut decoder = decoder(FRAMES);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+    assert_eq!(decoder.last_telegram_count(), 2);
+    assert!(decoder.decode().unwrap().is_none());
+  }
+
+  #[test]
+  fn wrong_key() {
+    let mut decoder = Decoder::new(Dlms::new([0; 16]));
+    decoder.push(FRAMES);
+    decoder.push(FRAMES);
+
+    assert!(matches!(decoder.decode(), Err(Error::DecryptionFailed(None))));
+    assert!(matches!(decoder.decode(), Err(Error::DecryptionFailed(None))));
+    assert!(decoder.decode().unwrap().is_none());
+    assert_eq!(decoder.stats().decryption_failures(), 2);
+  }
+
+  #[test]
+  fn wrong_key_skipped() {
+    let mut decoder = Decoder::new(Dlms::new([0; 16])).skip_decryption_failures(true).capture_failures(true);
+    decoder.push(FRAMES);
+
+    assert!(decoder.decode().unwrap().is_none());
+    assert_eq!(decoder.stats().decryption_failures(), 1);
+  }
+
+  #[test]
+  fn malformed_apdus() {
+    const ENERGY: [u8; 6] = [1, 0, 1, 8, 0, 255];
+
+    let apdus = [
+      // Unknown APDU tag.
+      vec![0x42, 0x00],
+      // General-glo-ciphering with a payload shorter than the security header.
+      [[0xdb, 0x08].as_slice(), &[0; 8], &[0x02, 0x21]].concat(),
+      // Data type not implemented by `dlms_cosem`.
+      notification(&[(ENERGY, &[0x11, 0x01], None)]),
+      // Scaler overflowing in `dlms_cosem`.
+      notification(&[(ENERGY, &[0x06, 0x00, 0x00, 0x00, 0x01], Some((10, 30)))]),
+      notification(&[(ENERGY, &[0x06, 0x00, 0x00, 0x00, 0x01], Some((-128, 30)))]),
+    ];
+
+    for apdu in apdus {
+      let mut decoder = decoder(&[plain_frame(&apdu), FRAMES.to_vec()].concat());
+      assert_eq!(decoder.decode().unwrap().unwrap().len(), 15, "{:02x?}", apdu);
+    }
+  }
+
+  #[test]
+  fn plain_apdu() {
+    let apdu = notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x30, 0x39], Some((0, 30)))]);
+    let mut decoder = decoder(&plain_frame(&apdu));
+    let obis_map = decoder.decode().unwrap().unwrap();
+    assert_eq!(decoder.last_quality(), Quality::default());
+    let register = &obis_map[&ObisCode::new(1, 0, 1, 8, 0, 255)];
+    assert_eq!(register.value(), &Data::Float64(12345.0));
+  }
+
+  #[test]
+  fn decode_frame_complete() {
+    let (len, apdu) = super::decode_frame(&[&[0x00, 0xff], FRAMES].concat(), KEY).unwrap();
+    assert_eq!(len, FRAMES.len() + 2);
+    assert!(matches!(apdu, Apdu::DataNotification(_)));
+  }
+
+  #[test]
+  fn decode_frame_truncated() {
+    match super::decode_frame(&FRAMES[..300], KEY) {
+      Err(Error::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof),
+      result => panic!("unexpected result: {:?}", result),
+    }
+  }
+
+  #[test]
+  fn decode_frame_wrong_key() {
+    assert!(matches!(super::decode_frame(FRAMES, [0; 16]), Err(Error::InvalidFormat)));
+  }
+
+  #[test]
+  fn capacity_limit() {
+    let mut decoder = Decoder::with_capacity_limit(Dlms::new(KEY), 522);
+    assert_eq!(decoder.push(FRAMES), FRAMES.len());
+    assert_eq!(decoder.push(FRAMES), 522 - FRAMES.len());
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+  }
+
+  #[test]
+  fn frame_too_large() {
+    let mut decoder = Decoder::with_capacity_limit(Dlms::new(KEY), 300);
+    let capacity = decoder.buffer.capacity();
+
+    let mut bytes = [FRAMES, FRAMES].concat();
+    let mut results = Vec::new();
+    while !bytes.is_empty() {
+      let len = decoder.push(&bytes);
+      bytes.drain(..len);
+      assert!(decoder.buffer.len() <= 300);
+
+      while let Some(result) = decoder.decode().transpose() {
+        results.push(result);
+      }
+    }
+
+    assert!(matches!(results.as_slice(), [Err(Error::FrameTooLarge), Err(Error::FrameTooLarge)]), "{:?}", results);
+    assert_eq!(decoder.buffer.capacity(), capacity);
+  }
+
+  #[test]
+  fn resync_after_garbage() {
+    let garbage = [0x00, 0x68, 0x03, 0x10, 0xe5, 0x16, 0xff];
+    let mut decoder = decoder(&[&garbage, FRAMES].concat());
+
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+    assert_eq!(decoder.stats().bytes_read() - decoder.stats().useful_bytes(), garbage.len() as u64);
+  }
+
+  #[test]
+  fn resync_baud_mismatch() {
+    let mut decoder = decoder(&[0x00; 100]).baud_mismatch_threshold(Some(50));
+
+    assert!(matches!(decoder.decode(), Err(Error::LikelyBaudMismatch)));
+    assert!(decoder.decode().unwrap().is_none());
+  }
+
+  #[test]
+  fn lenient_start() {
+    let frames = [&[0x01, 0x02], &FRAMES[1..]].concat();
+
+    assert!(decoder(&frames).decode().unwrap().is_none());
+
+    let mut decoder = decoder(&frames).lenient_start(true);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+    assert!(decoder.last_quality().checksum_ok);
+  }
+
+  #[test]
+  fn lenient_start_incomplete() {
+    let mut decoder = decoder(&[0x01, FRAMES[1]]).lenient_start(true);
+
+    assert!(decoder.decode().unwrap().is_none());
+    decoder.push(&FRAMES[2..]);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+  }
+
+  /// A bridge header `BR`, waiting for the second byte after a `B`.
+  fn bridge_header(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+      [b'B', b'R', ..] | [b'B'] => Some(2),
+      _ => None,
+    }
+  }
+
+  #[test]
+  fn transport_header() {
+    let (frame_1, frame_2) = FRAMES.split_at(FRAME_1_LEN);
+    let frames = [b"BR", frame_1, b"BR", frame_2].concat();
+
+    assert!(decoder(&frames).decode().unwrap().is_none());
+
+    let mut decoder = decoder(&frames).with_transport_header(bridge_header);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+    assert_eq!(decoder.last_telegram_count(), 2);
+    assert!(decoder.decode().unwrap().is_none());
+  }
+
+  #[test]
+  fn transport_header_incomplete() {
+    let mut decoder = decoder(b"B").with_transport_header(bridge_header);
+
+    assert!(decoder.decode().unwrap().is_none());
+    assert_eq!(decoder.bytes_needed(), 1);
+    decoder.push(b"R");
+    decoder.push(FRAMES);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+  }
+
+  #[test]
+  fn transport_header_malformed() {
+    // Bytes which are not a header are skipped, as without a parser.
+    let mut malformed = decoder(&[b"BX", FRAMES].concat()).with_transport_header(bridge_header);
+    assert_eq!(malformed.decode().unwrap().unwrap().len(), 15);
+
+    // A header longer than the buffer is no header.
+    let mut limited = Decoder::with_capacity_limit(Dlms::new(KEY), FRAMES.len() + 2).with_transport_header(|_| Some(FRAMES.len() + 3));
+    limited.push(&[b"BR", FRAMES].concat());
+    assert_eq!(limited.decode().unwrap().unwrap().len(), 15);
+
+    // An empty header is no header.
+    let mut empty = decoder(&[b"BR", FRAMES].concat()).with_transport_header(|_| Some(0));
+    assert_eq!(empty.decode().unwrap().unwrap().len(), 15);
+  }
+
+  #[test]
+  fn replay() {
+    let mut decoder = decoder(&[FRAMES, FRAMES].concat()).replay_window(0);
+
+    assert!(decoder.decode().unwrap().is_some());
+    assert_eq!(decoder.last_quality().invocation_counter, invocation_counter(&decoder.last_apdu));
+    assert!(decoder.last_quality().invocation_counter.is_some());
+    assert!(matches!(decoder.decode(), Err(Error::Replayed)));
+    assert!(decoder.decode().unwrap().is_none());
+  }
+
+  #[test]
+  fn replay_window() {
+    let mut decoder = Decoder::new(Dlms::new(KEY)).replay_window(2);
+
+    for (counter, ok) in [(10, true), (12, true), (11, true), (9, false), (12, false), (13, true), (11, false), (10, false)] {
+      decoder.last_apdu = [[0xdb, 0x08].as_slice(), &[0; 8], &[0x82, 0x01, 0x00, 0x21], &u32::to_be_bytes(counter)].concat();
+      assert_eq!(decoder.check_replay().is_ok(), ok, "counter {}", counter);
+    }
+  }
+
+  #[test]
+  fn broadcast_key() {
+    let frames = patch_frames(SECURITY_CONTROL, FRAMES[SECURITY_CONTROL] | 0x40);
+
+    let mut decoder = decoder(&frames);
+    assert!(matches!(decoder.decode(), Err(Error::MissingBroadcastKey)));
+    assert!(decoder.decode().unwrap().is_none());
+
+    let mut decoder = Decoder::new(Dlms::new([0; 16])).with_broadcast_key(Dlms::new(KEY));
+    decoder.push(&frames);
+    decoder.push(FRAMES);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+    assert!(matches!(decoder.decode(), Err(Error::DecryptionFailed(_))));
+  }
+
+  #[test]
+  fn skip_frame() {
+    let mut decoder = decoder(&[&[0x00], FRAMES, FRAMES].concat());
+
+    assert!(decoder.skip_frame().unwrap());
+    assert!(decoder.skip_frame().unwrap());
+    assert_eq!(decoder.stats().skipped_frames(), 2);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+    assert!(!decoder.skip_frame().unwrap());
+    assert_eq!(decoder.stats().skipped_frames(), 2);
+    assert_eq!(decoder.stats().decryption_failures(), 0);
+  }
+
+  #[test]
+  fn set_dlms() {
+    let other_key = [0x01; 16];
+    let mut decoder = decoder(FRAMES).replay_window(0);
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+
+    decoder.push(&frames_with_key(other_key));
+    decoder.set_dlms(Dlms::new(other_key));
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+
+    decoder.push(FRAMES);
+    assert!(matches!(decoder.decode(), Err(Error::DecryptionFailed(_))));
+  }
+
+  #[test]
+  fn compact_array() {
+    let frame = plain_frame(&notification_body(COMPACT_ARRAY));
+
+    let mut decoder = decoder(&[frame.as_slice(), FRAMES].concat());
+    assert!(matches!(decoder.decode(), Err(Error::CompactArray)));
+    assert_eq!(decoder.decode().unwrap().unwrap().len(), 15);
+
+    assert!(matches!(decode_frame(&frame, KEY), Err(Error::CompactArray)));
+
+    // The APDU follows the STSAP and DTSAP.
+    let tree = DataTree::from_apdu(&frame[9..(frame.len() - 2)]).unwrap();
+    assert_eq!(tree, DataTree::List(vec![
+      DataTree::List(vec![DataTree::Bytes(vec![0x01, 0x00, 0x01, 0x08, 0x00, 0xff]), DataTree::Unsigned(100)]),
+      DataTree::List(vec![DataTree::Bytes(vec![0x01, 0x00, 0x02, 0x08, 0x00, 0xff]), DataTree::Unsigned(200)]),
+    ]));
+  }
+}
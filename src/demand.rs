@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+use dlms_cosem::{DateTime, ObisCode, Unit};
+
+use crate::{apply_scaler_f64, DataTree, Error, ObisKey};
+
+/// A maximum demand register (e.g. 1-0:1.6.0) with the time the maximum was captured.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaxDemand {
+  /// The scaled value.
+  pub value: f64,
+  /// The unit, if reported by the meter.
+  pub unit: Option<Unit>,
+  /// The time the maximum occurred.
+  pub captured_at: DateTime,
+}
+
+fn as_f64(tree: &DataTree) -> Option<f64> {
+  match *tree {
+    DataTree::Integer(n) => Some(n as f64),
+    DataTree::Unsigned(n) => Some(n as f64),
+    DataTree::Float(n) => Some(n),
+    _ => None,
+  }
+}
+
+/// Parse an extended register, i.e. code, value, scaler and unit and capture time, of a maximum demand.
+fn max_demand(items: &[DataTree]) -> Option<(ObisCode, MaxDemand)> {
+  let (code, value, scaler_unit, captured_at) = match items {
+    [DataTree::Bytes(code), value, DataTree::List(scaler_unit), captured_at] => (code, value, scaler_unit, captured_at),
+    _ => return None,
+  };
+
+  let (_, code) = ObisCode::parse(code).ok().filter(|(rest, _)| rest.is_empty())?;
+  // Value group D 6 is maximum demand.
+  if ObisKey::from(&code).groups()[3] != 6 {
+    return None
+  }
+
+  let (scaler, unit) = match scaler_unit.as_slice() {
+    [DataTree::Integer(scaler), DataTree::Unsigned(unit)] => (*scaler, *unit),
+    _ => return None,
+  };
+
+  let value = apply_scaler_f64(as_f64(value)?, i8::try_from(scaler).ok()?);
+  let unit = u8::try_from(unit).ok().and_then(|unit| Unit::try_from(unit).ok());
+  let captured_at = captured_at.as_date_time()?;
+
+  Some((code, MaxDemand { value, unit, captured_at }))
+}
+
+fn collect(tree: &DataTree, demands: &mut BTreeMap<ObisCode, MaxDemand>) {
+  if let DataTree::List(items) = tree {
+    demands.extend(items.windows(4).filter_map(max_demand));
+    items.iter().for_each(|item| collect(item, demands));
+  }
+}
+
+/// Decode the maximum demand registers contained in a plain data-notification APDU, see [`decrypt_apdu`](crate::decrypt_apdu).
+///
+/// `ObisMap` only contains the value of these registers, while the capture time is dropped.
+/// Registers are recognized both as nested structure and inline in the notification body.
+pub fn parse_max_demands(apdu: &[u8]) -> Result<BTreeMap<ObisCode, MaxDemand>, Error> {
+  let tree = DataTree::from_apdu(apdu)?;
+
+  let mut demands = BTreeMap::new();
+  collect(&tree, &mut demands);
+  Ok(demands)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::fixture::{notification, notification_body};
+
+  const CAPTURED_AT: [u8; 12] = [0x07, 0xe5, 0x09, 0x0a, 0x05, 0x12, 0x0f, 0x00, 0x00, 0xff, 0x88, 0x80];
+
+  /// An extended register with a value of 1234 and a scaler of -3.
+  fn extended_register(code: [u8; 6]) -> Vec<u8> {
+    let mut register = vec![0x09, 0x06];
+    register.extend(code);
+    register.extend([0x06, 0x00, 0x00, 0x04, 0xd2, 0x02, 0x02, 0x0f, 0xfd, 0x16, 0x1b, 0x09, 0x0c]);
+    register.extend(CAPTURED_AT);
+    register
+  }
+
+  fn expected() -> MaxDemand {
+    MaxDemand { value: 1.234, unit: Some(Unit::Watt), captured_at: DateTime::parse(&CAPTURED_AT).unwrap().1 }
+  }
+
+  #[test]
+  fn nested() {
+    let apdu = notification_body(&[&[0x02, 0x02, 0x02, 0x04][..], &extended_register([1, 0, 1, 6, 0, 255]), &[0x02, 0x04], &extended_register([1, 0, 2, 6, 0, 255])].concat());
+    let demands = parse_max_demands(&apdu).unwrap();
+
+    assert_eq!(demands.len(), 2);
+    assert_eq!(demands[&ObisCode::new(1, 0, 1, 6, 0, 255)], expected());
+    assert_eq!(demands[&ObisCode::new(1, 0, 2, 6, 0, 255)], expected());
+  }
+
+  #[test]
+  fn inline() {
+    let apdu = notification_body(&[&[0x02, 0x08][..], &extended_register([1, 0, 1, 6, 0, 255]), &extended_register([1, 0, 1, 8, 0, 255])].concat());
+    let demands = parse_max_demands(&apdu).unwrap();
+
+    assert_eq!(demands, BTreeMap::from([(ObisCode::new(1, 0, 1, 6, 0, 255), expected())]));
+  }
+
+  #[test]
+  fn invalid() {
+    assert!(parse_max_demands(&[0x0f, 0x00]).is_err());
+    assert!(parse_max_demands(&notification(&[])).unwrap().is_empty());
+  }
+}
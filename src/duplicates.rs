@@ -0,0 +1,193 @@
+use std::collections::BTreeMap;
+
+use dlms_cosem::{Apdu, ObisCode, ObisMap, Register};
+
+use crate::Error;
+use crate::apdu::check_decodable;
+use crate::tree::split_list;
+
+/// How [`parse_registers`] handles codes occurring multiple times in one APDU.
+///
+/// `ObisMap`, and therefore [`SmartMeter`](crate::SmartMeter), keeps the last occurrence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateCodes {
+  /// Keep the first occurrence.
+  KeepFirst,
+  /// Keep the last occurrence.
+  #[default]
+  KeepLast,
+  /// Keep all occurrences, in the order they appear.
+  CollectAll,
+}
+
+/// The encoding of a single register in a notification body.
+struct RawRegister {
+  code: ObisCode,
+  /// The number of elements of the notification body.
+  elements: usize,
+  bytes: Vec<u8>,
+}
+
+/// Split a notification body into registers, either nested structures or
+/// a code followed by the value and an optional scaler and unit.
+fn registers(elements: &[&[u8]]) -> Option<Vec<RawRegister>> {
+  let code = |element: &[u8]| match element {
+    [0x09, 6, code @ ..] => ObisCode::parse(code).ok().filter(|(rest, _)| rest.is_empty()).map(|(_, code)| code),
+    _ => None,
+  };
+  let is_scaler_unit = |element: &[u8]| matches!(element, [0x02, 2, 0x0f, _, 0x16, _]);
+
+  let mut registers = Vec::new();
+  let mut elements = elements.iter().peekable();
+
+  while let Some(&element) = elements.next() {
+    let register = match code(element) {
+      Some(code) => {
+        let mut bytes = [element, elements.next()?].concat();
+        let mut count = 2;
+
+        if let Some(scaler_unit) = elements.next_if(|element| is_scaler_unit(element)) {
+          bytes.extend(*scaler_unit);
+          count += 1;
+        }
+
+        RawRegister { code, elements: count, bytes }
+      },
+      None => {
+        let code = code(split_list(element)?.first()?)?;
+        RawRegister { code, elements: 1, bytes: element.to_vec() }
+      },
+    };
+
+    registers.push(register);
+  }
+
+  Some(registers)
+}
+
+/// Decode the given registers using `dlms_cosem`, by rebuilding the APDU with only these registers.
+fn decode(header: &[u8], registers: &[&RawRegister]) -> Result<ObisMap, Error> {
+  // Like `dlms_cosem`, the number of elements is a single byte.
+  let elements = registers.iter().map(|register| register.elements).sum::<usize>();
+  let elements = u8::try_from(elements).map_err(|_| Error::InvalidFormat)?;
+
+  let mut apdu = header.to_vec();
+  apdu.extend([0x02, elements]);
+  for register in registers {
+    apdu.extend(&register.bytes);
+  }
+
+  check_decodable(&apdu)?;
+
+  match Apdu::parse(&apdu) {
+    Ok((_, apdu)) => ObisMap::parse(&apdu).map(|(_, obis_map)| obis_map).map_err(|_| Error::InvalidFormat),
+    Err(_) => Err(Error::InvalidFormat),
+  }
+}
+
+/// Decode the registers of a plain data-notification APDU, see [`decrypt_apdu`](crate::decrypt_apdu),
+/// handling codes occurring multiple times according to `duplicates`.
+///
+/// Unless collecting all occurrences, every code maps to exactly one register.
+pub fn parse_registers(apdu: &[u8], duplicates: DuplicateCodes) -> Result<BTreeMap<ObisCode, Vec<Register>>, Error> {
+  let header_len = match apdu {
+    [0x0f, _, _, _, _, date_time_len, ..] => 6 + usize::from(*date_time_len),
+    _ => return Err(Error::InvalidFormat),
+  };
+  let (header, body) = apdu.split_at_checked(header_len).ok_or(Error::InvalidFormat)?;
+
+  let elements = split_list(body).ok_or(Error::InvalidFormat)?;
+  let registers = registers(&elements).ok_or(Error::InvalidFormat)?;
+
+  // Each group contains every code at most once, so it can be decoded into an `ObisMap`.
+  let mut groups: Vec<Vec<&RawRegister>> = Vec::new();
+  let mut occurrences = BTreeMap::<&ObisCode, usize>::new();
+
+  let mut ordered = registers.iter().collect::<Vec<_>>();
+  if duplicates == DuplicateCodes::KeepLast {
+    ordered.reverse();
+  }
+
+  for register in ordered {
+    let occurrence = occurrences.entry(&register.code).or_insert(0);
+    if groups.len() <= *occurrence {
+      groups.push(Vec::new());
+    }
+    groups[*occurrence].push(register);
+    *occurrence += 1;
+  }
+
+  if duplicates != DuplicateCodes::CollectAll {
+    groups.truncate(1);
+  }
+
+  let mut map = BTreeMap::<ObisCode, Vec<Register>>::new();
+  for group in groups {
+    for (code, register) in decode(header, &group)?.iter() {
+      map.entry(code.clone()).or_default().push(register.clone());
+    }
+  }
+
+  Ok(map)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use dlms_cosem::Data;
+
+  use crate::fixture::notification;
+
+  const ENERGY: [u8; 6] = [1, 0, 1, 8, 0, 255];
+  const POWER: [u8; 6] = [1, 0, 1, 7, 0, 255];
+
+  fn values(duplicates: DuplicateCodes) -> Vec<Data> {
+    let apdu = notification(&[
+      (ENERGY, &[0x06, 0x00, 0x00, 0x00, 0x01], Some((0, 30))),
+      (POWER, &[0x12, 0x00, 0x64], None),
+      (ENERGY, &[0x06, 0x00, 0x00, 0x00, 0x02], Some((0, 30))),
+    ]);
+
+    let map = parse_registers(&apdu, duplicates).unwrap();
+    assert_eq!(map[&ObisCode::new(1, 0, 1, 7, 0, 255)].len(), 1);
+    map[&ObisCode::new(1, 0, 1, 8, 0, 255)].iter().map(|register| register.value().clone()).collect()
+  }
+
+  #[test]
+  fn keep_first() {
+    assert_eq!(values(DuplicateCodes::KeepFirst), [Data::Float64(1.0)]);
+  }
+
+  #[test]
+  fn keep_last() {
+    assert_eq!(values(DuplicateCodes::KeepLast), [Data::Float64(2.0)]);
+  }
+
+  #[test]
+  fn collect_all() {
+    assert_eq!(values(DuplicateCodes::CollectAll), [Data::Float64(1.0), Data::Float64(2.0)]);
+  }
+
+  #[test]
+  fn default_matches_obis_map() {
+    assert_eq!(DuplicateCodes::default(), DuplicateCodes::KeepLast);
+  }
+
+  #[test]
+  fn invalid() {
+    assert!(matches!(parse_registers(&[0x0f, 0x00], DuplicateCodes::KeepLast), Err(Error::InvalidFormat)));
+  }
+
+  #[test]
+  fn many_registers() {
+    // 43 registers with scaler and unit and one without, i.e. 131 elements.
+    let mut registers = (0..43).map(|e| ([1, 0, 1, 8, e, 255], [0x06, 0x00, 0x00, 0x00, e].to_vec(), Some((0, 30)))).collect::<Vec<_>>();
+    registers.push((POWER, vec![0x12, 0x00, 0x64], None));
+    let registers = registers.iter().map(|(code, value, scaler_unit)| (*code, value.as_slice(), *scaler_unit)).collect::<Vec<_>>();
+
+    let map = parse_registers(&notification(&registers), DuplicateCodes::KeepLast).unwrap();
+    assert_eq!(map.len(), 44);
+    assert_eq!(map[&ObisCode::new(1, 0, 1, 8, 42, 255)][0].value(), &Data::Float64(42.0));
+  }
+}
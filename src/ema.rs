@@ -0,0 +1,116 @@
+use std::collections::BTreeMap;
+
+use dlms_cosem::{ObisCode, ObisMap};
+
+use crate::value::as_f64;
+
+/// Tracks an exponential moving average of the values of selected codes.
+///
+/// The first value of a code is used as is, every following value `x`
+/// updates the average to `alpha * x + (1 - alpha) * average`.
+#[derive(Debug, Clone)]
+pub struct EmaTracker {
+  alpha: f64,
+  averages: BTreeMap<ObisCode, Option<f64>>,
+}
+
+impl EmaTracker {
+  /// Create a tracker for the given codes.
+  ///
+  /// `alpha` is the weight of new values and is clamped to `0.0..=1.0`.
+  /// A NaN `alpha` is treated as `1.0`, i.e. the average is always the latest value.
+  pub fn new(codes: impl IntoIterator<Item = ObisCode>, alpha: f64) -> Self {
+    let averages = codes.into_iter().map(|code| (code, None)).collect();
+    let alpha = if alpha.is_nan() { 1.0 } else { alpha.clamp(0.0, 1.0) };
+    Self { alpha, averages }
+  }
+
+  /// Update the averages with a reading and return them.
+  ///
+  /// Codes missing from the reading keep their previous average.
+  pub fn update(&mut self, obis_map: &ObisMap) -> BTreeMap<ObisCode, f64> {
+    for (code, average) in self.averages.iter_mut() {
+      let value = match obis_map.get(code).and_then(|reg| as_f64(reg.value())) {
+        Some(value) => value,
+        None => continue,
+      };
+
+      *average = Some(match *average {
+        Some(average) => self.alpha * value + (1.0 - self.alpha) * average,
+        None => value,
+      });
+    }
+
+    self.averages.iter().filter_map(|(code, average)| Some((code.clone(), (*average)?))).collect()
+  }
+
+  /// The current average for the given code.
+  pub fn get(&self, code: &ObisCode) -> Option<f64> {
+    *self.averages.get(code)?
+  }
+
+  /// Forget all averages, so the next values are used as seeds again.
+  pub fn reset(&mut self) {
+    self.averages.values_mut().for_each(|average| *average = None);
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::fixture::{notification, obis_map};
+
+  const ENERGY: ObisCode = ObisCode::new(1, 0, 1, 8, 0, 255);
+  const POWER: ObisCode = ObisCode::new(1, 0, 1, 7, 0, 255);
+
+  fn power(value: u8) -> ObisMap {
+    obis_map(&notification(&[([1, 0, 1, 7, 0, 255], &[0x06, 0x00, 0x00, 0x00, value], None)]))
+  }
+
+  #[test]
+  fn update() {
+    let mut ema = EmaTracker::new([POWER], 0.25);
+    assert_eq!(ema.get(&POWER), None);
+
+    // The first value is the seed.
+    assert_eq!(ema.update(&power(100)), BTreeMap::from([(POWER, 100.0)]));
+    // 0.25 * 200 + 0.75 * 100
+    assert_eq!(ema.update(&power(200)), BTreeMap::from([(POWER, 125.0)]));
+    // 0.25 * 25 + 0.75 * 125
+    assert_eq!(ema.update(&power(25)).get(&POWER), Some(&100.0));
+    assert_eq!(ema.get(&POWER), Some(100.0));
+  }
+
+  #[test]
+  fn missing_codes() {
+    let mut ema = EmaTracker::new([POWER, ENERGY], 0.5);
+
+    // Codes which were never seen have no average.
+    assert_eq!(ema.update(&power(100)), BTreeMap::from([(POWER, 100.0)]));
+    assert_eq!(ema.get(&ENERGY), None);
+
+    // Codes missing from a reading keep their average.
+    let energy = obis_map(&notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x10], None)]));
+    assert_eq!(ema.update(&energy), BTreeMap::from([(POWER, 100.0), (ENERGY, 16.0)]));
+  }
+
+  #[test]
+  fn reset() {
+    let mut ema = EmaTracker::new([POWER], 0.5);
+    ema.update(&power(100));
+    ema.reset();
+
+    assert_eq!(ema.get(&POWER), None);
+    assert_eq!(ema.update(&power(10)), BTreeMap::from([(POWER, 10.0)]));
+  }
+
+  #[test]
+  fn alpha() {
+    for (alpha, expected) in [(2.0, 200.0), (-1.0, 100.0), (f64::NAN, 200.0)] {
+      let mut ema = EmaTracker::new([POWER], alpha);
+      ema.update(&power(100));
+      assert_eq!(ema.update(&power(200)).get(&POWER), Some(&expected), "alpha {}", alpha);
+    }
+  }
+}
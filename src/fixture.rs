@@ -0,0 +1,116 @@
+//! Fixtures shared by the unit tests.
+
+use dlms_cosem::{Apdu, ObisMap};
+
+use crate::{decrypt_apdu, Reading};
+
+/// One reading of an EVN/Kaifa meter split into two encrypted M-Bus long frames, also used by the benchmarks.
+pub(crate) const FRAMES: &[u8] = include_bytes!("../benches/fixtures/frames.bin");
+
+/// The key `FRAMES` is encrypted with.
+pub(crate) const KEY: [u8; 16] = [0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe, 0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe];
+
+/// The length of the first frame of `FRAMES`.
+pub(crate) const FRAME_1_LEN: usize = 256;
+
+/// The offset of the security control byte in `FRAMES`.
+pub(crate) const SECURITY_CONTROL: usize = 22;
+
+/// A register of a data-notification: the OBIS code, the encoded value and an optional scaler and unit.
+pub(crate) type Register<'a> = ([u8; 6], &'a [u8], Option<(i8, u8)>);
+
+/// Encode a plain data-notification APDU with the given registers.
+pub(crate) fn notification(registers: &[Register<'_>]) -> Vec<u8> {
+  let elements = registers.iter().map(|(_, _, scaler_unit)| if scaler_unit.is_some() { 3 } else { 2 }).sum::<usize>();
+
+  // Long-invoke-id-and-priority and date-time 2021-09-11T09:13:20.
+  let mut apdu = vec![0x0f, 0x00, 0x00, 0x00, 0x01, 0x0c, 0x07, 0xe5, 0x09, 0x0b, 0x06, 0x09, 0x0d, 0x14, 0x00, 0xff, 0x88, 0x80];
+  apdu.extend([0x02, elements as u8]);
+
+  for (code, value, scaler_unit) in registers {
+    apdu.extend([0x09, 0x06]);
+    apdu.extend(code);
+    apdu.extend(*value);
+
+    if let Some((scaler, unit)) = scaler_unit {
+      apdu.extend([0x02, 0x02, 0x0f, *scaler as u8, 0x16, *unit]);
+    }
+  }
+
+  apdu
+}
+
+/// Encode a plain data-notification APDU with an arbitrary notification body.
+pub(crate) fn notification_body(body: &[u8]) -> Vec<u8> {
+  let mut apdu = notification(&[]);
+  apdu.truncate(apdu.len() - 2);
+  apdu.extend(body);
+  apdu
+}
+
+/// A compact array of the values 100 and 200 of a structure of a code and a long-unsigned,
+/// as sent by meters which use compact arrays for their registers.
+pub(crate) const COMPACT_ARRAY: &[u8] = &[
+  0x13, 0x02, 0x02, 0x09, 0x12, 0x12,
+  0x06, 0x01, 0x00, 0x01, 0x08, 0x00, 0xff, 0x00, 0x64,
+  0x06, 0x01, 0x00, 0x02, 0x08, 0x00, 0xff, 0x00, 0xc8,
+];
+
+/// Decode the registers of a plain data-notification APDU.
+pub(crate) fn obis_map(apdu: &[u8]) -> ObisMap {
+  let (_, apdu) = Apdu::parse(apdu).unwrap();
+  ObisMap::parse(&apdu).unwrap().1
+}
+
+/// Decode a reading with the given registers.
+pub(crate) fn reading(registers: &[Register<'_>]) -> Reading {
+  Reading::from(obis_map(&notification(registers)))
+}
+
+/// Encode a single M-Bus long frame.
+pub(crate) fn long_frame(control_information: u8, user_data: &[u8]) -> Vec<u8> {
+  let len = user_data.len() + 3;
+  let mut frame = vec![0x68, len as u8, len as u8, 0x68, 0x53, 0xff, control_information];
+  frame.extend(user_data);
+  frame.push(frame[4..].iter().fold(0u8, |sum, &b| sum.wrapping_add(b)));
+  frame.push(0x16);
+  frame
+}
+
+/// Encode an unencrypted APDU as a single, unsegmented frame.
+pub(crate) fn plain_frame(apdu: &[u8]) -> Vec<u8> {
+  long_frame(0x10, &[[0x01, 0x67].as_slice(), apdu].concat())
+}
+
+/// Replace a byte in the first frame of `FRAMES`, fixing its checksum.
+pub(crate) fn patch_frames(offset: usize, byte: u8) -> Vec<u8> {
+  let mut frames = FRAMES.to_vec();
+  frames[offset] = byte;
+  frames[FRAME_1_LEN - 2] = frames[4..(FRAME_1_LEN - 2)].iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+  frames
+}
+
+/// Encrypt the reading in `FRAMES` with another key.
+pub(crate) fn frames_with_key(key: [u8; 16]) -> Vec<u8> {
+  // The APDU is split across the user data of both frames, following the STSAP and DTSAP.
+  let ranges = [9..(FRAME_1_LEN - 2), (FRAME_1_LEN + 9)..(FRAMES.len() - 2)];
+  let apdu = ranges.iter().flat_map(|range| FRAMES[range.clone()].iter().copied()).collect::<Vec<_>>();
+
+  // The payload follows the tag, system title, length, security control byte and invocation counter.
+  // `dlms_cosem` decrypts without checking the tag, so decrypting the plain text encrypts it.
+  let (header, _) = apdu.split_at(18);
+  let plain = decrypt_apdu(&apdu, KEY).unwrap();
+  let encrypted = decrypt_apdu(&[header, &plain].concat(), key).unwrap();
+  let mut apdu = header.iter().chain(&encrypted).copied();
+
+  let mut frames = FRAMES.to_vec();
+  for range in ranges {
+    for byte in &mut frames[range.clone()] {
+      *byte = apdu.next().unwrap();
+    }
+
+    let frame_start = range.start - 9;
+    frames[range.end] = frames[(frame_start + 4)..range.end].iter().fold(0u8, |sum, &b| sum.wrapping_add(b));
+  }
+  frames
+}
@@ -0,0 +1,179 @@
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::{Error, SerialConfig};
+
+const STX: u8 = 0x02;
+const ETX: u8 = 0x03;
+const ACK: u8 = 0x06;
+
+/// The identification message sent by a meter in response to a sign-on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identification {
+  manufacturer: String,
+  baud_rate: u32,
+  identification: String,
+}
+
+impl Identification {
+  /// The three-letter manufacturer ID.
+  pub fn manufacturer(&self) -> &str {
+    &self.manufacturer
+  }
+
+  /// The highest baud rate supported by the meter.
+  pub fn baud_rate(&self) -> u32 {
+    self.baud_rate
+  }
+
+  /// The meter identification.
+  pub fn identification(&self) -> &str {
+    &self.identification
+  }
+}
+
+/// A data set of an IEC 62056-21 data readout, e.g. `1.8.0(001234.5*kWh)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataSet {
+  /// The address, usually an OBIS code without the A and B groups.
+  pub address: String,
+  pub value: String,
+  pub unit: Option<String>,
+}
+
+/// An IEC 62056-21 mode C session, e.g. for optical probes.
+///
+/// The session signs on at 300 baud with 7E1, reads the identification
+/// and switches to the baud rate proposed by the meter for the data readout.
+pub struct ModeCSession {
+  port: Box<dyn SerialPort>,
+  identification: Identification,
+}
+
+impl std::fmt::Debug for ModeCSession {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    f.debug_struct("ModeCSession")
+      .field("port", &self.port.name())
+      .field("identification", &self.identification)
+      .finish()
+  }
+}
+
+fn baud_rate(c: u8) -> Option<u32> {
+  Some(match c {
+    b'0' => 300,
+    b'1' => 600,
+    b'2' => 1200,
+    b'3' => 2400,
+    b'4' => 4800,
+    b'5' => 9600,
+    b'6' => 19200,
+    _ => return None,
+  })
+}
+
+fn read_byte(port: &mut dyn SerialPort) -> io::Result<u8> {
+  let mut byte = [0];
+  port.read_exact(&mut byte)?;
+  Ok(byte[0])
+}
+
+fn read_line(port: &mut dyn SerialPort) -> io::Result<Vec<u8>> {
+  let mut line = Vec::new();
+
+  loop {
+    line.push(read_byte(port)?);
+
+    if line.ends_with(b"\r\n") {
+      line.truncate(line.len() - 2);
+      return Ok(line)
+    }
+  }
+}
+
+impl ModeCSession {
+  /// Open `path` and sign on, optionally addressing a specific device.
+  ///
+  /// The port should have a read timeout, see [`serialport::SerialPortBuilder::timeout`].
+  pub fn open(path: &str, timeout: Duration, device_address: Option<&str>) -> Result<Self, Error> {
+    let port = SerialConfig::new_7e1(300).builder(path).timeout(timeout).open().map_err(io::Error::from)?;
+    Self::sign_on(port, device_address)
+  }
+
+  /// Sign on using an already opened port, configured for 300 baud with 7E1.
+  pub fn sign_on(mut port: Box<dyn SerialPort>, device_address: Option<&str>) -> Result<Self, Error> {
+    write!(port, "/?{}!\r\n", device_address.unwrap_or(""))?;
+    port.flush()?;
+
+    let line = read_line(&mut *port)?;
+    let identification = match line.as_slice() {
+      [b'/', m1, m2, m3, baud_char, identification @ ..] => Identification {
+        manufacturer: String::from_utf8_lossy(&[*m1, *m2, *m3]).into_owned(),
+        baud_rate: baud_rate(*baud_char).ok_or(Error::InvalidFormat)?,
+        identification: String::from_utf8_lossy(identification).into_owned(),
+      },
+      _ => return Err(Error::InvalidFormat),
+    };
+
+    // Acknowledge with protocol control 0 (normal), the proposed baud rate and mode 0 (data readout).
+    let ack = [ACK, b'0', line[4], b'0', b'\r', b'\n'];
+    port.write_all(&ack)?;
+    port.flush()?;
+
+    // Wait until the acknowledgement was sent at 300 baud before switching, 10 bits per character.
+    thread::sleep(Duration::from_millis(ack.len() as u64 * 10 * 1000 / 300 + 50));
+    port.set_baud_rate(identification.baud_rate).map_err(io::Error::from)?;
+
+    Ok(Self { port, identification })
+  }
+
+  /// The identification received during sign-on.
+  pub fn identification(&self) -> &Identification {
+    &self.identification
+  }
+
+  /// Read the data block and check its block check character.
+  pub fn read_data(&mut self) -> Result<Vec<DataSet>, Error> {
+    while read_byte(&mut *self.port)? != STX {}
+
+    let mut block = Vec::new();
+    loop {
+      let byte = read_byte(&mut *self.port)?;
+      block.push(byte);
+
+      if byte == ETX {
+        break
+      }
+    }
+
+    let bcc = read_byte(&mut *self.port)?;
+    if block.iter().fold(0, |bcc, b| bcc ^ b) != bcc {
+      return Err(Error::InvalidFormat)
+    }
+
+    let block = String::from_utf8_lossy(&block);
+    let data_sets = block.lines()
+      .take_while(|line| !line.starts_with('!'))
+      .filter_map(|line| {
+        let (address, rest) = line.split_once('(')?;
+        let value = rest.strip_suffix(')')?.split(")(").next()?;
+        let (value, unit) = match value.split_once('*') {
+          Some((value, unit)) => (value, Some(unit.to_owned())),
+          None => (value, None),
+        };
+
+        Some(DataSet { address: address.to_owned(), value: value.to_owned(), unit })
+      })
+      .collect();
+
+    Ok(data_sets)
+  }
+
+  /// Get back the underlying port.
+  pub fn into_inner(self) -> Box<dyn SerialPort> {
+    self.port
+  }
+}
@@ -0,0 +1,433 @@
+use std::collections::btree_map;
+use std::io::Read;
+use std::mem;
+use std::time::SystemTime;
+
+use dlms_cosem::{DateTime, ObisCode, ObisMap, Register};
+
+use crate::{Clock, Error, Quality, SmartMeter, SystemClock};
+use crate::reading::timestamp;
+use crate::value::as_f64;
+
+/// Adapters for iterators over readings.
+pub trait ObisIterator: Iterator<Item = Result<ObisMap, Error>> + Sized {
+  /// Flatten readings into individual registers, each paired with the meter clock of its reading.
+  fn flatten_codes(self) -> FlattenCodes<Self> {
+    FlattenCodes { iter: self, current: None }
+  }
+
+  /// Tag every item with a sequence number.
+  ///
+  /// The sequence number starts at 0 and is incremented after each successful reading,
+  /// so an error is tagged with the number the next reading will get.
+  /// It is kept per iterator and is never reset.
+  fn numbered(self) -> Numbered<Self> {
+    Numbered { iter: self, seq: 0 }
+  }
+
+  /// Skip readings whose values did not change compared to the last yielded reading.
+  ///
+  /// Registers can be excluded from the comparison with [`DedupValues::ignore`],
+  /// e.g. the clock, and numeric values can be given a tolerance with [`DedupValues::tolerance`].
+  fn dedup_values(self) -> DedupValues<Self> {
+    DedupValues { iter: self, last: None, ignored: Vec::new(), tolerances: Vec::new() }
+  }
+
+  /// Yield the first reading in full and afterwards only the registers which changed.
+  ///
+  /// After `resync_interval` diffs, a full reading is yielded again, so consumers can recover
+  /// from a lost item. A full reading is also yielded when a register disappears,
+  /// since a diff cannot express removals.
+  fn diffs(self, resync_interval: usize) -> Diffs<Self> {
+    Diffs { iter: self, last: None, resync_interval, since_full: 0 }
+  }
+
+  /// Pair every item with the wall-clock time it was received.
+  ///
+  /// This is the time the host decoded the reading, not the meter clock.
+  fn timestamped(self) -> Timestamped<Self, SystemClock> {
+    self.timestamped_with(SystemClock)
+  }
+
+  /// Like [`timestamped`](Self::timestamped), but takes the time from `clock`.
+  fn timestamped_with<C: Clock>(self, clock: C) -> Timestamped<Self, C> {
+    Timestamped { iter: self, clock }
+  }
+}
+
+impl<I: Iterator<Item = Result<ObisMap, Error>>> ObisIterator for I {}
+
+/// Iterator returned by [`ObisIterator::flatten_codes`].
+#[derive(Debug)]
+pub struct FlattenCodes<I> {
+  iter: I,
+  current: Option<(btree_map::IntoIter<ObisCode, Register>, Option<DateTime>)>,
+}
+
+impl<I: Iterator<Item = Result<ObisMap, Error>>> Iterator for FlattenCodes<I> {
+  type Item = Result<(ObisCode, Register, Option<DateTime>), Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      if let Some((registers, timestamp)) = &mut self.current {
+        if let Some((code, reg)) = registers.next() {
+          return Some(Ok((code, reg, timestamp.clone())))
+        }
+      }
+
+      let mut obis_map = match self.iter.next()? {
+        Ok(obis_map) => obis_map,
+        Err(err) => return Some(Err(err)),
+      };
+
+      let timestamp = timestamp(&obis_map);
+      self.current = Some((mem::take(&mut *obis_map).into_iter(), timestamp));
+    }
+  }
+}
+
+/// Iterator returned by [`ObisIterator::numbered`].
+#[derive(Debug)]
+pub struct Numbered<I> {
+  iter: I,
+  seq: u64,
+}
+
+impl<I: Iterator<Item = Result<ObisMap, Error>>> Iterator for Numbered<I> {
+  type Item = (u64, Result<ObisMap, Error>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let item = self.iter.next()?;
+    let seq = self.seq;
+
+    if item.is_ok() {
+      self.seq += 1;
+    }
+
+    Some((seq, item))
+  }
+}
+
+/// Iterator returned by [`ObisIterator::timestamped`].
+#[derive(Debug)]
+pub struct Timestamped<I, C> {
+  iter: I,
+  clock: C,
+}
+
+impl<I: Iterator<Item = Result<ObisMap, Error>>, C: Clock> Iterator for Timestamped<I, C> {
+  type Item = (SystemTime, Result<ObisMap, Error>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let item = self.iter.next()?;
+    Some((self.clock.system_time(), item))
+  }
+}
+
+/// Iterator returned by [`ObisIterator::dedup_values`].
+#[derive(Debug)]
+pub struct DedupValues<I> {
+  iter: I,
+  last: Option<ObisMap>,
+  ignored: Vec<ObisCode>,
+  tolerances: Vec<(ObisCode, f64)>,
+}
+
+impl<I> DedupValues<I> {
+  /// Do not consider changes of the given register.
+  pub fn ignore(mut self, code: ObisCode) -> Self {
+    self.ignored.push(code);
+    self
+  }
+
+  /// Only consider changes of the given numeric register larger than `tolerance`.
+  pub fn tolerance(mut self, code: ObisCode, tolerance: f64) -> Self {
+    self.tolerances.push((code, tolerance));
+    self
+  }
+
+  fn changed(&self, last: &ObisMap, obis_map: &ObisMap) -> bool {
+    let tracked = |(code, _): &(&ObisCode, &Register)| !self.ignored.contains(code);
+
+    if last.iter().filter(tracked).count() != obis_map.iter().filter(tracked).count() {
+      return true
+    }
+
+    obis_map.iter().filter(tracked).any(|(code, reg)| {
+      let last_reg = match last.get(code) {
+        Some(last_reg) => last_reg,
+        None => return true,
+      };
+
+      let tolerance = self.tolerances.iter().find(|(c, _)| c == code).map(|(_, t)| *t);
+
+      match (tolerance, as_f64(last_reg.value()), as_f64(reg.value())) {
+        (Some(tolerance), Some(a), Some(b)) => (a - b).abs() > tolerance,
+        _ => last_reg.value() != reg.value(),
+      }
+    })
+  }
+}
+
+impl<I: Iterator<Item = Result<ObisMap, Error>>> Iterator for DedupValues<I> {
+  type Item = Result<ObisMap, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let obis_map = match self.iter.next()? {
+        Ok(obis_map) => obis_map,
+        Err(err) => return Some(Err(err)),
+      };
+
+      if self.last.as_ref().is_some_and(|last| !self.changed(last, &obis_map)) {
+        continue
+      }
+
+      self.last = Some(obis_map.clone());
+      return Some(Ok(obis_map))
+    }
+  }
+}
+
+/// An item yielded by [`ObisIterator::diffs`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReadingDiff {
+  /// Whether `changed` contains all registers and replaces any previous state.
+  pub full: bool,
+  /// The registers which changed since the previous item, or all registers if `full` is set.
+  pub changed: ObisMap,
+}
+
+/// Iterator returned by [`ObisIterator::diffs`].
+#[derive(Debug)]
+pub struct Diffs<I> {
+  iter: I,
+  last: Option<ObisMap>,
+  resync_interval: usize,
+  since_full: usize,
+}
+
+impl<I: Iterator<Item = Result<ObisMap, Error>>> Iterator for Diffs<I> {
+  type Item = Result<ReadingDiff, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let obis_map = match self.iter.next()? {
+      Ok(obis_map) => obis_map,
+      Err(err) => return Some(Err(err)),
+    };
+
+    let mut changed = obis_map.clone();
+
+    let full = match &self.last {
+      Some(last) if self.since_full < self.resync_interval && last.keys().all(|code| obis_map.contains_key(code)) => {
+        changed.retain(|code, reg| last.get(code) != Some(reg));
+        self.since_full += 1;
+        false
+      },
+      _ => {
+        self.since_full = 0;
+        true
+      },
+    };
+
+    self.last = Some(obis_map);
+    Some(Ok(ReadingDiff { full, changed }))
+  }
+}
+
+/// Iterator returned by [`SmartMeter::with_quality`].
+#[derive(Debug)]
+pub struct WithQuality<R> {
+  pub(crate) smart_meter: SmartMeter<R>,
+}
+
+impl<R> WithQuality<R> {
+  /// Get back the underlying [`SmartMeter`].
+  pub fn into_inner(self) -> SmartMeter<R> {
+    self.smart_meter
+  }
+}
+
+impl<R: Read> Iterator for WithQuality<R> {
+  type Item = Result<(Quality, ObisMap), Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let item = self.smart_meter.next()?;
+    Some(item.map(|obis_map| (self.smart_meter.last_quality(), obis_map)))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::io::Cursor;
+  use std::time::Duration;
+
+  use dlms_cosem::Dlms;
+
+  use crate::MockClock;
+
+  use crate::fixture::{notification, obis_map, FRAMES, KEY};
+
+  const CLOCK: ObisCode = ObisCode::new(0, 0, 1, 0, 0, 255);
+  const ENERGY: ObisCode = ObisCode::new(1, 0, 1, 8, 0, 255);
+  const POWER: ObisCode = ObisCode::new(1, 0, 1, 7, 0, 255);
+
+  /// The date-time 2021-09-11T09:13:20.
+  const DATE_TIME: [u8; 12] = [0x07, 0xe5, 0x09, 0x0b, 0x06, 0x09, 0x0d, 0x14, 0x00, 0xff, 0x88, 0x80];
+
+  fn double_long_unsigned(value: u32) -> Vec<u8> {
+    [[0x06].as_slice(), &value.to_be_bytes()].concat()
+  }
+
+  /// A reading with the given energy and, if given, power.
+  fn energy(energy: u32, power: Option<u32>) -> ObisMap {
+    let energy = double_long_unsigned(energy);
+    let power = power.map(double_long_unsigned);
+
+    let mut registers = vec![([1, 0, 1, 8, 0, 255], energy.as_slice(), None)];
+    if let Some(power) = &power {
+      registers.push(([1, 0, 1, 7, 0, 255], power.as_slice(), None));
+    }
+
+    obis_map(&notification(&registers))
+  }
+
+  #[test]
+  fn with_quality() {
+    let smart_meter = SmartMeter::new(Cursor::new([FRAMES, FRAMES].concat()), Dlms::new(KEY)).replay_window(0);
+    let items = smart_meter.with_quality().collect::<Vec<_>>();
+
+    match items.as_slice() {
+      [Ok((quality, _)), Err(Error::Replayed), Err(Error::NoProgress)] => {
+        assert!(quality.checksum_ok);
+        assert!(quality.invocation_counter.is_some());
+      },
+      items => panic!("unexpected items: {:?}", items),
+    }
+  }
+
+  #[test]
+  fn flatten_codes() {
+    let clock = [[0x09, 0x0c].as_slice(), &DATE_TIME].concat();
+    let with_clock = obis_map(&notification(&[
+      ([0, 0, 1, 0, 0, 255], &clock, None),
+      ([1, 0, 1, 8, 0, 255], &double_long_unsigned(1), None),
+    ]));
+
+    let items = vec![Ok(with_clock), Err(Error::InvalidFormat), Ok(energy(2, Some(3)))];
+    let flattened = items.into_iter().flatten_codes().collect::<Vec<_>>();
+
+    let date_time = DateTime::parse(&DATE_TIME).unwrap().1;
+    match flattened.as_slice() {
+      [
+        Ok((clock, _, Some(t1))), Ok((energy_1, reg_1, Some(t2))),
+        Err(Error::InvalidFormat),
+        // Registers are yielded ordered by code.
+        Ok((power, reg_2, None)), Ok((energy_2, reg_3, None)),
+      ] => {
+        assert_eq!((clock, energy_1, power, energy_2), (&CLOCK, &ENERGY, &POWER, &ENERGY));
+        assert_eq!((t1, t2), (&date_time, &date_time));
+        assert_eq!([reg_1, reg_2, reg_3].map(|reg| as_f64(reg.value())), [Some(1.0), Some(3.0), Some(2.0)]);
+      },
+      items => panic!("unexpected items: {:?}", items),
+    }
+  }
+
+  #[test]
+  fn numbered() {
+    let items = vec![Ok(energy(1, None)), Err(Error::InvalidFormat), Ok(energy(2, None)), Err(Error::NoProgress), Ok(energy(3, None))];
+    let numbered = items.into_iter().numbered().map(|(seq, item)| (seq, item.is_ok())).collect::<Vec<_>>();
+
+    // Errors get the number of the next reading.
+    assert_eq!(numbered, [(0, true), (1, false), (1, true), (2, false), (2, true)]);
+  }
+
+  fn energies(items: impl Iterator<Item = Result<ObisMap, Error>>) -> Vec<Option<f64>> {
+    items.map(|item| item.ok().and_then(|obis_map| as_f64(obis_map[&ENERGY].value()))).collect()
+  }
+
+  #[test]
+  fn dedup_values() {
+    let items = vec![Ok(energy(100, Some(1))), Ok(energy(100, Some(1))), Err(Error::InvalidFormat), Ok(energy(101, Some(1)))];
+    assert_eq!(energies(items.into_iter().dedup_values()), [Some(100.0), None, Some(101.0)]);
+  }
+
+  #[test]
+  fn dedup_values_tolerance() {
+    let items = vec![Ok(energy(100, Some(1))), Ok(energy(103, Some(2))), Ok(energy(106, Some(3))), Ok(energy(106, None))];
+    let deduped = items.into_iter().dedup_values().tolerance(ENERGY, 5.0).ignore(POWER);
+
+    // Changes are compared against the last yielded reading, so they add up.
+    assert_eq!(energies(deduped), [Some(100.0), Some(106.0)]);
+  }
+
+  #[test]
+  fn dedup_values_codes_changed() {
+    let items = vec![Ok(energy(1, None)), Ok(energy(1, Some(5))), Ok(energy(1, Some(5))), Ok(energy(1, None))];
+    let deduped = items.into_iter().dedup_values().map(|item| item.unwrap().contains_key(&POWER)).collect::<Vec<_>>();
+
+    assert_eq!(deduped, [false, true, false]);
+  }
+
+  #[test]
+  fn timestamped() {
+    let start = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000);
+    let clock = MockClock::new(start);
+
+    // Decoding takes a second, the time is taken once an item was received.
+    let items = vec![Ok(energy(1, None)), Err(Error::InvalidFormat)].into_iter().inspect({
+      let clock = clock.clone();
+      move |_| clock.advance(Duration::from_secs(1))
+    });
+
+    let times = items.timestamped_with(clock).map(|(time, item)| (time, item.is_ok())).collect::<Vec<_>>();
+    assert_eq!(times, [(start + Duration::from_secs(1), true), (start + Duration::from_secs(2), false)]);
+
+    let before = SystemTime::now();
+    let (time, _) = vec![Ok(energy(1, None))].into_iter().timestamped().next().unwrap();
+    assert!(before <= time && time <= SystemTime::now());
+  }
+
+  #[test]
+  fn diffs() {
+    let items = vec![
+      Ok(energy(1, Some(1))),
+      Ok(energy(2, Some(1))),
+      Err(Error::InvalidFormat),
+      Ok(energy(2, Some(1))),
+      Ok(energy(3, Some(1))),
+      Ok(energy(3, None)),
+      Ok(energy(3, Some(2))),
+    ];
+
+    let diffs = items.into_iter().diffs(2).map(|item| {
+      item.map(|diff| (diff.full, diff.changed.keys().cloned().collect::<Vec<_>>()))
+    }).collect::<Vec<_>>();
+
+    match diffs.as_slice() {
+      [
+        // The first reading is yielded in full.
+        Ok((true, first)),
+        Ok((false, changed)),
+        Err(Error::InvalidFormat),
+        Ok((false, unchanged)),
+        // A full reading after two diffs.
+        Ok((true, resync)),
+        // A full reading after the power disappeared.
+        Ok((true, removed)),
+        // The power reappearing is a change.
+        Ok((false, added)),
+      ] => {
+        assert_eq!(first, &[POWER, ENERGY]);
+        assert_eq!(changed, &[ENERGY]);
+        assert_eq!(unchanged, &[]);
+        assert_eq!(resync, &[POWER, ENERGY]);
+        assert_eq!(removed, &[ENERGY]);
+        assert_eq!(added, &[POWER]);
+      },
+      diffs => panic!("unexpected diffs: {:?}", diffs),
+    }
+  }
+}
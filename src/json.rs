@@ -0,0 +1,170 @@
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use dlms_cosem::{Data, ObisMap};
+
+use crate::{as_f64, format_value, Clock, Sink, SinkError, SystemClock};
+
+fn escape(s: &str) -> String {
+  let mut escaped = String::with_capacity(s.len());
+
+  for c in s.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+
+  escaped
+}
+
+/// Format a value as a JSON number if it is numeric and finite, or as a string otherwise.
+fn value(value: &Data) -> String {
+  let numeric = matches!(value,
+    Data::Integer(_) | Data::Unsigned(_) | Data::Long(_) | Data::LongUnsigned(_) |
+    Data::DoubleLong(_) | Data::DoubleLongUnsigned(_) | Data::Long64(_) | Data::Long64Unsigned(_) |
+    Data::Float32(_) | Data::Float64(_) | Data::Enum(_)
+  );
+
+  match as_f64(value) {
+    Some(n) if numeric && n.is_finite() => n.to_string(),
+    _ => format!(r#""{}""#, escape(&format_value(value))),
+  }
+}
+
+fn fields(obis_map: &ObisMap) -> impl Iterator<Item = String> + '_ {
+  obis_map.iter().map(|(key, reg)| {
+    let unit = reg.unit().and_then(|u| u.as_str()).unwrap_or("");
+    format!(r#""{}":{{"value":{},"unit":"{}"}}"#, key, value(reg.value()), escape(unit))
+  })
+}
+
+/// Convert a reading to a JSON object mapping each code to its value and unit,
+/// e.g. `{"1-0:1.8.0*255":{"value":5308437,"unit":"Wh"}}`.
+///
+/// Numeric values are written as numbers, all others as formatted strings, see [`format_value`].
+pub fn to_json(obis_map: &ObisMap) -> String {
+  format!("{{{}}}", fields(obis_map).collect::<Vec<_>>().join(","))
+}
+
+/// Writes readings as newline-delimited JSON, see [`to_json`].
+///
+/// Each line additionally contains a `timestamp` field with the time the reading
+/// was written in seconds since the Unix epoch. Lines are flushed immediately.
+pub struct JsonLinesWriter<W> {
+  writer: W,
+  clock: Arc<dyn Clock + Send + Sync>,
+}
+
+impl<W: fmt::Debug> fmt::Debug for JsonLinesWriter<W> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("JsonLinesWriter")
+      .field("writer", &self.writer)
+      .finish_non_exhaustive()
+  }
+}
+
+impl<W: Write> JsonLinesWriter<W> {
+  pub fn new(writer: W) -> Self {
+    Self::with_clock(writer, SystemClock)
+  }
+
+  /// Create a writer which takes timestamps from `clock`.
+  pub fn with_clock(writer: W, clock: impl Clock + Send + Sync + 'static) -> Self {
+    Self { writer, clock: Arc::new(clock) }
+  }
+
+  /// Write a reading as a single line.
+  pub fn write(&mut self, obis_map: &ObisMap) -> io::Result<()> {
+    let timestamp = self.clock.system_time().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    let mut line = format!(r#"{{"timestamp":{:.3}"#, timestamp.as_secs_f64());
+    for field in fields(obis_map) {
+      line.push(',');
+      line.push_str(&field);
+    }
+    line.push_str("}\n");
+
+    self.writer.write_all(line.as_bytes())?;
+    self.writer.flush()
+  }
+
+  /// Get back the underlying writer.
+  pub fn into_inner(self) -> W {
+    self.writer
+  }
+}
+
+impl<W: Write> Sink for JsonLinesWriter<W> {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    Ok(self.write(reading)?)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::time::{Duration, SystemTime};
+
+  use crate::MockClock;
+  use crate::fixture::{notification, obis_map};
+
+  /// A writer which fails every write.
+  struct Failing;
+
+  impl Write for Failing {
+    fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+      Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+      Ok(())
+    }
+  }
+
+  #[test]
+  fn escape_control_characters() {
+    assert_eq!(escape("a\"b\\c"), r#"a\"b\\c"#);
+    assert_eq!(escape("\n\t\u{0}\u{7f}"), r#"\u000a\u0009\u0000\u007f"#);
+    assert_eq!(escape("€"), "€");
+  }
+
+  #[test]
+  fn values() {
+    assert_eq!(value(&Data::LongUnsigned(1234)), "1234");
+    assert_eq!(value(&Data::Integer(-5)), "-5");
+    assert_eq!(value(&Data::Float64(0.5)), "0.5");
+    assert_eq!(value(&Data::Float64(f64::NAN)), r#""NaN""#);
+    assert_eq!(value(&Data::OctetString(b"123".to_vec())), r#""OctetString([49, 50, 51])""#);
+  }
+
+  #[test]
+  fn write_line() {
+    let obis_map = obis_map(&notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x04, 0xd2], Some((0, 30)))]));
+
+    let clock = MockClock::new(SystemTime::UNIX_EPOCH + Duration::from_millis(1500));
+    let mut writer = JsonLinesWriter::with_clock(Vec::new(), clock.clone());
+    writer.write(&obis_map).unwrap();
+    clock.advance(Duration::from_secs(1));
+    writer.write(&obis_map).unwrap();
+
+    assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), concat!(
+      r#"{"timestamp":1.500,"1-0:1.8.0*255":{"value":1234,"unit":"Wh"}}"#, "\n",
+      r#"{"timestamp":2.500,"1-0:1.8.0*255":{"value":1234,"unit":"Wh"}}"#, "\n",
+    ));
+  }
+
+  #[test]
+  fn write_error() {
+    let obis_map = obis_map(&notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x04, 0xd2], Some((0, 30)))]));
+
+    let mut writer = JsonLinesWriter::new(Failing);
+    assert_eq!(writer.write(&obis_map).unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    assert_eq!(writer.consume(&obis_map).unwrap_err().to_string(), "broken pipe");
+  }
+}
@@ -1,110 +1,836 @@
 #![deny(missing_debug_implementations)]
 
-use std::io::{self, Read};
+use std::io::{self, Read, Write};
 use std::fmt;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use dlms_cosem::{Error as DlmsError, Dlms, ObisMap};
-use mbusparse::{Error as MbusError, Telegram};
+use dlms_cosem::{Dlms, ObisCode, ObisMap};
+
+mod apdu;
+pub use apdu::{apply_scalers, decrypt_apdu};
+mod cache;
+pub use cache::LatestCache;
+mod capture;
+pub use capture::{CaptureFile, RollingCapture};
+mod clock;
+pub use clock::{Clock, SystemClock, MockClock};
+pub mod convert;
+mod csv;
+pub use csv::CsvWriter;
+mod decoder;
+pub use decoder::{decode_frame, ChecksumVerifier, Decoder, DebugState, Quality, TransportHeader, DEFAULT_BAUD_MISMATCH_THRESHOLD};
+mod demand;
+pub use demand::{parse_max_demands, MaxDemand};
+mod duplicates;
+pub use duplicates::{parse_registers, DuplicateCodes};
+mod ema;
+pub use ema::EmaTracker;
+#[cfg(test)]
+mod fixture;
+#[cfg(feature = "serialport")]
+mod iec;
+#[cfg(feature = "serialport")]
+pub use iec::{DataSet, Identification, ModeCSession};
+mod iter;
+pub use iter::{ObisIterator, DedupValues, Diffs, FlattenCodes, Numbered, ReadingDiff, Timestamped, WithQuality};
+mod json;
+pub use json::{to_json, JsonLinesWriter};
+mod load_profile;
+pub use load_profile::{parse_load_profile, ProfileEntry};
+mod merge;
+pub use merge::{merge, Merge, MultiMeter, Source};
+mod obis_key;
+pub use obis_key::ObisKey;
+pub mod obis_registry;
+#[cfg(feature = "serde")]
+mod output;
+#[cfg(feature = "serde")]
+pub use output::{build_sinks, Output, OutputConfig, OutputConfigError};
+mod poll;
+pub use poll::{NonBlocking, Poll};
+mod profile;
+pub use profile::Profile;
+mod profiler;
+pub use profiler::{CodeInfo, CodeProfiler};
+mod prometheus;
+pub use prometheus::{to_prometheus, PrometheusTextfile};
+#[cfg(feature = "protobuf")]
+pub mod proto;
+mod reading;
+pub use reading::{BreakerState, ClockStatus, EquipmentId, MissingCode, ObisMapExt, RemapCollision, Reading, Phase, PowerFailureInfo};
+#[cfg(feature = "serialport")]
+mod serial;
+#[cfg(feature = "serialport")]
+pub use serial::SerialConfig;
+mod sink;
+pub use sink::{FanOut, Sink, SinkError};
+mod soak;
+pub use soak::SoakReport;
+mod spawn;
+pub use spawn::{Backpressure, ReaderHandle};
+mod stats;
+pub use stats::Stats;
+mod transform;
+mod tree;
+pub use tree::DataTree;
+pub use transform::{Transform, MedianFilter, RoundFloats};
+mod transport;
+pub use transport::{Endian, LengthPrefixedTransport};
+mod unit;
+pub use unit::UnitExt;
+mod value;
+pub use value::{apply_scaler, apply_scaler_f64, as_f64, format_value};
 
 #[derive(Debug)]
 pub enum Error {
   Io(io::Error),
-  DecryptionFailed,
+  InvalidFormat,
+  /// Decryption failed, with the raw APDU if [`SmartMeter::capture_failures`] is enabled.
+  DecryptionFailed(Option<Vec<u8>>),
+  LikelyBaudMismatch,
+  Replayed,
+  Deadline,
+  MissingBroadcastKey,
+  /// The reader repeatedly returned no bytes while a frame was still incomplete.
+  NoProgress,
+  /// A reading did not fit into the buffer, see [`Decoder::with_capacity_limit`].
+  FrameTooLarge,
+  /// A reading contains a compact array, which `dlms_cosem` cannot decode, see [`DataTree`].
+  CompactArray,
 }
 
 impl fmt::Display for Error {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       Self::Io(err) => err.fmt(f),
-      Self::DecryptionFailed => write!(f, "decryption failed"),
+      Self::InvalidFormat => write!(f, "invalid format"),
+      Self::DecryptionFailed(_) => write!(f, "decryption failed"),
+      Self::LikelyBaudMismatch => write!(f, "no valid frame found, baud rate is likely wrong"),
+      Self::Replayed => write!(f, "invocation counter was replayed"),
+      Self::Deadline => write!(f, "reading deadline exceeded"),
+      Self::MissingBroadcastKey => write!(f, "no broadcast key provided"),
+      Self::NoProgress => write!(f, "reader made no progress"),
+      Self::FrameTooLarge => write!(f, "frame exceeds buffer capacity"),
+      Self::CompactArray => write!(f, "compact arrays are not supported"),
     }
   }
 }
 
 impl std::error::Error for Error {}
 
+impl From<io::Error> for Error {
+  fn from(err: io::Error) -> Self {
+    Self::Io(err)
+  }
+}
+
+struct Watchdog<R> {
+  threshold: usize,
+  failures: usize,
+  reopen: Box<dyn FnMut() -> io::Result<R> + Send>,
+}
+
+type FrameCallback = Box<dyn FnMut(&[u8]) + Send>;
+
+/// Number of consecutive recoverable errors skipped by [`SmartMeter::collect_readings`].
+const MAX_SKIPPED_ERRORS: usize = 8;
+
+/// Number of meter timestamps used by [`SmartMeter::observed_interval`].
+const OBSERVED_READINGS: usize = 8;
+
+/// Number of consecutive empty reads retried before [`Error::NoProgress`] is returned.
+const MAX_STALLED_READS: usize = 3;
+
+/// Delay before retrying an empty read.
+const STALL_DELAY: Duration = Duration::from_millis(10);
+
 #[derive(Debug)]
+struct Retry {
+  max_retries: usize,
+  kinds: Vec<io::ErrorKind>,
+}
+
 pub struct SmartMeter<R> {
   reader: R,
-  dlms: Dlms,
-  buffer: Vec<u8>,
+  decoder: Decoder,
+  transforms: Vec<Box<dyn Transform + Send>>,
+  watchdog: Option<Watchdog<R>>,
+  retry: Option<Retry>,
+  reading_deadline: Option<Duration>,
+  on_frame: Option<FrameCallback>,
+  observed_timestamps: Vec<i64>,
+  equipment_id: Option<EquipmentId>,
+  /// `None` for the system clock, which needs neither an allocation nor dynamic dispatch.
+  clock: Option<Arc<dyn Clock + Send + Sync>>,
+  exhausted: bool,
+}
+
+impl<R: fmt::Debug> fmt::Debug for SmartMeter<R> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("SmartMeter")
+      .field("reader", &self.reader)
+      .field("decoder", &self.decoder)
+      .field("transforms", &self.transforms.len())
+      .field("watchdog", &self.watchdog.as_ref().map(|w| (w.threshold, w.failures)))
+      .field("retry", &self.retry)
+      .field("reading_deadline", &self.reading_deadline)
+      .field("on_frame", &self.on_frame.is_some())
+      .field("observed_timestamps", &self.observed_timestamps)
+      .field("equipment_id", &self.equipment_id)
+      .field("exhausted", &self.exhausted)
+      .finish_non_exhaustive()
+  }
 }
 
 impl<R> SmartMeter<R> {
   pub fn new(reader: R, dlms: Dlms) -> Self {
-    SmartMeter { reader, dlms, buffer: Vec::new() }
+    Self::with_capacity(reader, dlms, 0)
+  }
+
+  /// Create a new instance with an internal buffer pre-allocated for `capacity` bytes,
+  /// see [`Decoder::with_capacity`].
+  pub fn with_capacity(reader: R, dlms: Dlms, capacity: usize) -> Self {
+    Self::with_decoder(reader, Decoder::with_capacity(dlms, capacity))
+  }
+
+  /// Create a new instance with an internal buffer of at most `limit` bytes which never grows,
+  /// see [`Decoder::with_capacity_limit`].
+  pub fn with_capacity_limit(reader: R, dlms: Dlms, limit: usize) -> Self {
+    Self::with_decoder(reader, Decoder::with_capacity_limit(dlms, limit))
+  }
+
+  fn with_decoder(reader: R, decoder: Decoder) -> Self {
+    SmartMeter { reader, decoder, transforms: Vec::new(), watchdog: None, retry: None, reading_deadline: None, on_frame: None, observed_timestamps: Vec::new(), equipment_id: None, clock: None, exhausted: false }
+  }
+
+  /// Reopen the reader using `reopen` after `threshold` consecutive decryption failures.
+  ///
+  /// The internal buffer is cleared when the reader is reopened.
+  /// The failure counter is reset on every successful reading.
+  pub fn with_watchdog(
+    mut self,
+    threshold: usize,
+    reopen: impl FnMut() -> io::Result<R> + Send + 'static,
+  ) -> Self {
+    self.watchdog = Some(Watchdog { threshold, failures: 0, reopen: Box::new(reopen) });
+    self
+  }
+
+  /// Retry a failed read up to `max_retries` times if its error is one of `kinds`,
+  /// e.g. [`io::ErrorKind::WouldBlock`] or [`io::ErrorKind::TimedOut`].
+  ///
+  /// [`io::ErrorKind::Interrupted`] is always retried.
+  pub fn with_retry(mut self, max_retries: usize, kinds: &[io::ErrorKind]) -> Self {
+    self.retry = Some(Retry { max_retries, kinds: kinds.to_vec() });
+    self
+  }
+
+  /// Limit the total time spent reading a single reading, including resynchronization,
+  /// after which [`Error::Deadline`] is returned.
+  ///
+  /// The deadline is only checked between reads, so `reader` should have a short read timeout.
+  pub fn reading_deadline(mut self, deadline: Duration) -> Self {
+    self.reading_deadline = Some(deadline);
+    self
+  }
+
+  /// Take the time for deadlines and timeouts from `clock`, e.g. a [`MockClock`] in tests.
+  ///
+  /// Timeouts are only checked between reads, so the clock can be advanced from within the reader.
+  pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+    self.clock = Some(Arc::new(clock));
+    self
+  }
+
+  /// The current time of the clock set with [`with_clock`](Self::with_clock).
+  pub(crate) fn now(&self) -> Instant {
+    match &self.clock {
+      Some(clock) => clock.now(),
+      None => Instant::now(),
+    }
+  }
+
+  /// Call `f` with every raw M-Bus telegram of each decoded reading, e.g. to capture traffic
+  /// using a [`RollingCapture`].
+  pub fn on_frame(mut self, f: impl FnMut(&[u8]) + Send + 'static) -> Self {
+    self.on_frame = Some(Box::new(f));
+    self
+  }
+
+  /// Convert the value of `code` using `f` for every reading.
+  ///
+  /// Conversions run in insertion order, together with transforms added using [`with_transform`](Self::with_transform).
+  pub fn with_conversion(self, code: ObisCode, f: convert::Conversion) -> Self {
+    self.with_transform(transform::Convert(code, f))
+  }
+
+  /// Add a transform which is applied to every reading, in insertion order.
+  pub fn with_transform(mut self, transform: impl Transform + Send + 'static) -> Self {
+    self.transforms.push(Box::new(transform));
+    self
+  }
+
+  /// Set the number of bytes which may be skipped without decoding a reading
+  /// before [`Error::LikelyBaudMismatch`] is returned, or `None` to disable the check.
+  pub fn baud_mismatch_threshold(mut self, threshold: Option<usize>) -> Self {
+    self.decoder = self.decoder.baud_mismatch_threshold(threshold);
+    self
+  }
+
+  /// Verify M-Bus checksums using a custom verifier, see [`Decoder::with_checksum`].
+  pub fn with_checksum(mut self, verifier: ChecksumVerifier) -> Self {
+    self.decoder = self.decoder.with_checksum(verifier);
+    self
+  }
+
+  /// Strip transport headers added by a bridge, see [`Decoder::with_transport_header`].
+  pub fn with_transport_header(mut self, parser: TransportHeader) -> Self {
+    self.decoder = self.decoder.with_transport_header(parser);
+    self
+  }
+
+  /// Include the raw APDU in [`Error::DecryptionFailed`], see [`Decoder::capture_failures`].
+  pub fn capture_failures(mut self, capture: bool) -> Self {
+    self.decoder = self.decoder.capture_failures(capture);
+    self
+  }
+
+  /// Skip readings which fail to decrypt, see [`Decoder::skip_decryption_failures`].
+  ///
+  /// Skipped failures do not count towards the watchdog threshold.
+  pub fn skip_decryption_failures(mut self, skip: bool) -> Self {
+    self.decoder = self.decoder.skip_decryption_failures(skip);
+    self
+  }
+
+  /// Restore missing start characters in damaged captures, see [`Decoder::lenient_start`].
+  pub fn lenient_start(mut self, lenient: bool) -> Self {
+    self.decoder = self.decoder.lenient_start(lenient);
+    self
+  }
+
+  /// Hint the expected APDU size in bytes, see [`Decoder::payload_size_hint`].
+  pub fn payload_size_hint(mut self, size: usize) -> Self {
+    self.decoder = self.decoder.payload_size_hint(size);
+    self
+  }
+
+  /// Reject replayed readings, see [`Decoder::replay_window`].
+  pub fn replay_window(mut self, window: u32) -> Self {
+    self.decoder = self.decoder.replay_window(window);
+    self
+  }
+
+  /// Decrypt broadcast APDUs with a separate DLMS context, see [`Decoder::with_broadcast_key`].
+  pub fn with_broadcast_key(mut self, dlms: Dlms) -> Self {
+    self.decoder = self.decoder.with_broadcast_key(dlms);
+    self
+  }
+
+  /// Replace the DLMS context, e.g. after the key was changed, see [`Decoder::set_dlms`].
+  pub fn set_dlms(&mut self, dlms: Dlms) {
+    self.decoder.set_dlms(dlms);
+  }
+
+  /// The equipment identifier (0-0:96.1.0) of the first reading containing it.
+  pub fn equipment_id(&self) -> Option<&EquipmentId> {
+    self.equipment_id.as_ref()
+  }
+
+  /// The interval between readings, inferred from the meter clock (0-0:1.0.0) of the first readings.
+  ///
+  /// Returns `None` until at least three readings with a clock were decoded. The lower median
+  /// of the differences is used, so a single missed frame does not affect the result.
+  pub fn observed_interval(&self) -> Option<Duration> {
+    let mut intervals = self.observed_timestamps.windows(2)
+      .map(|t| t[1] - t[0])
+      .filter(|&d| d > 0)
+      .collect::<Vec<_>>();
+
+    if intervals.len() < 2 {
+      return None
+    }
+
+    intervals.sort_unstable();
+    let interval = intervals[(intervals.len() - 1) / 2];
+    Some(Duration::from_secs(interval as u64))
+  }
+
+  /// Statistics collected so far.
+  pub fn stats(&self) -> &Stats {
+    self.decoder.stats()
+  }
+
+  /// Reopen the reader using the function passed to [`with_watchdog`](Self::with_watchdog),
+  /// e.g. after the connection was lost.
+  ///
+  /// Returns `Ok(false)` if no watchdog is configured. The internal buffer is cleared on success.
+  pub fn reopen(&mut self) -> io::Result<bool> {
+    let watchdog = match &mut self.watchdog {
+      Some(watchdog) => watchdog,
+      None => return Ok(false),
+    };
+
+    self.reader = (watchdog.reopen)()?;
+    watchdog.failures = 0;
+    self.decoder.clear();
+    self.exhausted = false;
+    Ok(true)
+  }
+
+  /// Get mutable access to the underlying reader.
+  ///
+  /// Reading from it directly bypasses the internal buffer.
+  pub fn reader(&mut self) -> &mut R {
+    &mut self.reader
+  }
+
+  /// Get a snapshot of the current frame assembly state.
+  pub fn debug_state(&self) -> DebugState {
+    self.decoder.debug_state()
+  }
+
+  /// The buffered bytes not yet consumed as hex, see [`Decoder::buffer_hex`].
+  pub fn buffer_hex(&self) -> String {
+    self.decoder.buffer_hex()
+  }
+
+  /// The raw, still encrypted APDU of the last reading.
+  ///
+  /// Use [`decrypt_apdu`] to get the plain APDU bytes.
+  pub fn last_apdu(&self) -> &[u8] {
+    self.decoder.last_apdu()
+  }
+
+  /// The number of M-Bus telegrams the last reading was assembled from, see [`Decoder::last_telegram_count`].
+  ///
+  /// A change over time can indicate that the meter changed its fragmentation.
+  pub fn last_telegram_count(&self) -> usize {
+    self.decoder.last_telegram_count()
+  }
+
+  /// The quality of the last reading.
+  pub fn last_quality(&self) -> Quality {
+    self.decoder.last_quality()
+  }
+
+  /// Turn this into an iterator which tags every reading with its [`Quality`].
+  pub fn with_quality(self) -> WithQuality<R> {
+    WithQuality { smart_meter: self }
+  }
+}
+
+impl<R: Read> SmartMeter<R> {
+  /// Check whether `key` decrypts the traffic read from `reader`.
+  ///
+  /// Reads until the first complete APDU and checks whether it decrypts to a
+  /// plausible data-notification which can be decoded. Since the timeout is only checked between reads,
+  /// `reader` should have a read timeout itself.
+  pub fn validate_key(reader: R, key: [u8; 16], timeout: Duration) -> Result<bool, Error> {
+    let mut smart_meter = Self::new(reader, Dlms::new(key));
+    let deadline = smart_meter.now() + timeout;
+
+    loop {
+      if let Some(apdu) = smart_meter.decoder.decode_apdu()? {
+        return match decrypt_apdu(&apdu, key) {
+          // Compact arrays cannot be decoded, but are no sign of a wrong key.
+          Ok(apdu) => Ok(apdu::is_data_notification(&apdu) && matches!(apdu::check_decodable(&apdu), Ok(()) | Err(Error::CompactArray))),
+          Err(Error::InvalidFormat | Error::DecryptionFailed(_)) => Ok(false),
+          Err(err) => Err(err),
+        }
+      }
+
+      if smart_meter.now() > deadline {
+        return Err(io::Error::from(io::ErrorKind::TimedOut).into())
+      }
+
+      smart_meter.decoder.fill_from(&mut smart_meter.reader)?;
+    }
+  }
+
+  /// Read a single reading and release `reader` again.
+  ///
+  /// Partial frames at the start are skipped, as are frames without registers.
+  /// Frames buffered by the operating system are indistinguishable from current ones,
+  /// so e.g. serial port input buffers should be cleared beforehand.
+  /// Since the timeout is only checked between reads, `reader` should have a read timeout itself.
+  pub fn read_once(reader: R, dlms: Dlms, timeout: Duration) -> Result<ObisMap, Error> {
+    let mut smart_meter = Self::new(reader, dlms);
+    let deadline = smart_meter.now() + timeout;
+
+    loop {
+      let obis_map = smart_meter.read_obis_map(Some(deadline))?;
+
+      if !obis_map.is_empty() {
+        return Ok(obis_map)
+      }
+    }
+  }
+
+  fn decode(&mut self) -> Result<Option<ObisMap>, Error> {
+    let obis_map = match self.decoder.decode() {
+      Err(Error::DecryptionFailed(apdu)) => {
+        if let Some(watchdog) = &mut self.watchdog {
+          watchdog.failures += 1;
+
+          if watchdog.failures >= watchdog.threshold {
+            watchdog.failures = 0;
+            self.reader = (watchdog.reopen)()?;
+            self.decoder.clear();
+          }
+        }
+
+        return Err(Error::DecryptionFailed(apdu))
+      },
+      result => result?,
+    };
+
+    if let Some(mut obis_map) = obis_map {
+      if let Some(watchdog) = &mut self.watchdog {
+        watchdog.failures = 0;
+      }
+
+      if let Some(on_frame) = &mut self.on_frame {
+        self.decoder.last_frames().for_each(on_frame);
+      }
+
+      if self.equipment_id.is_none() {
+        self.equipment_id = reading::equipment_id(&obis_map);
+      }
+
+      if self.observed_timestamps.len() < OBSERVED_READINGS {
+        if let Some(seconds) = reading::timestamp(&obis_map).as_ref().and_then(reading::local_seconds) {
+          self.observed_timestamps.push(seconds);
+        }
+      }
+
+      for transform in self.transforms.iter_mut() {
+        transform.apply(&mut obis_map);
+      }
+
+      return Ok(Some(obis_map))
+    }
+
+    Ok(None)
+  }
+
+  fn read_obis_map(&mut self, deadline: Option<Instant>) -> Result<ObisMap, Error> {
+    let reading_deadline = self.reading_deadline.map(|d| self.now() + d);
+    let mut stalls = 0;
+
+    loop {
+      if let Some(obis_map) = self.decode()? {
+        return Ok(obis_map)
+      }
+
+      if deadline.is_some_and(|deadline| self.now() > deadline) {
+        return Err(io::Error::from(io::ErrorKind::TimedOut).into())
+      }
+
+      if reading_deadline.is_some_and(|deadline| self.now() > deadline) {
+        return Err(Error::Deadline)
+      }
+
+      self.fill_or_stall(&mut stalls)?;
+    }
+  }
+
+  /// Try getting the next reading from a non-blocking reader.
+  ///
+  /// Returns [`Poll::Pending`] if the reader has no more bytes available, i.e. returns
+  /// [`io::ErrorKind::WouldBlock`] or zero bytes, before a complete reading was received.
+  /// Bytes read so far are kept, so this can be called again once the reader is ready.
+  pub fn poll_obis_map(&mut self) -> Result<Poll<ObisMap>, Error> {
+    loop {
+      if let Some(obis_map) = self.decode()? {
+        return Ok(Poll::Ready(obis_map))
+      }
+
+      match self.fill() {
+        Ok(0) => return Ok(Poll::Pending),
+        Ok(_) => continue,
+        Err(err) if err.kind() == io::ErrorKind::WouldBlock => return Ok(Poll::Pending),
+        Err(err) => return Err(err.into()),
+      }
+    }
+  }
+
+  /// Turn this into an iterator which yields [`Poll::Pending`] instead of blocking,
+  /// see [`poll_obis_map`](Self::poll_obis_map).
+  pub fn non_blocking(self) -> NonBlocking<R> {
+    NonBlocking { smart_meter: self }
+  }
+
+  fn fill(&mut self) -> io::Result<usize> {
+    let mut retries = 0;
+
+    loop {
+      match self.decoder.fill_from(&mut self.reader) {
+        Err(err) if self.retry.as_ref().is_some_and(|retry| {
+          retries < retry.max_retries && retry.kinds.contains(&err.kind())
+        }) => retries += 1,
+        result => return result,
+      }
+    }
+  }
+
+  /// Fill the buffer, retrying empty reads after a short delay a few times before
+  /// giving up with [`Error::NoProgress`] instead of spinning on e.g. a reader at EOF.
+  fn fill_or_stall(&mut self, stalls: &mut usize) -> Result<(), Error> {
+    if self.fill()? > 0 {
+      *stalls = 0;
+      return Ok(())
+    }
+
+    *stalls += 1;
+    if *stalls > MAX_STALLED_READS {
+      return Err(Error::NoProgress)
+    }
+
+    thread::sleep(STALL_DELAY);
+    Ok(())
+  }
+
+  /// Read and discard `n` frames at the link layer without decrypting them.
+  ///
+  /// This is useful to fast-forward past a backlog when only the latest reading matters.
+  pub fn skip_frames(&mut self, n: usize) -> Result<(), Error> {
+    let mut stalls = 0;
+
+    for _ in 0..n {
+      while !self.decoder.skip_frame()? {
+        self.fill_or_stall(&mut stalls)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Get the next reading, skipping frames which do not contain any registers.
+  ///
+  /// This blocks until a reading is available or an error occurs.
+  pub fn read_reading(&mut self) -> Result<Reading, Error> {
+    loop {
+      let obis_map = self.read_obis_map(None)?;
+
+      if !obis_map.is_empty() {
+        return Ok(Reading::from(obis_map))
+      }
+    }
+  }
+
+  /// Read `n` readings, skipping frames which do not contain any registers.
+  ///
+  /// Errors caused by a single bad frame, i.e. [`Error::InvalidFormat`], [`Error::DecryptionFailed`],
+  /// [`Error::Replayed`], [`Error::MissingBroadcastKey`], [`Error::FrameTooLarge`] and [`Error::CompactArray`], are skipped
+  /// unless more than 8 occur in a row, in which case the last one is returned. All other errors are returned immediately.
+  pub fn collect_readings(&mut self, n: usize) -> Result<Vec<ObisMap>, Error> {
+    let mut readings = Vec::with_capacity(n);
+    let mut skipped_errors = 0;
+
+    while readings.len() < n {
+      match self.read_obis_map(None) {
+        Ok(obis_map) => {
+          skipped_errors = 0;
+
+          if !obis_map.is_empty() {
+            readings.push(obis_map);
+          }
+        },
+        Err(Error::InvalidFormat | Error::DecryptionFailed(_) | Error::Replayed | Error::MissingBroadcastKey | Error::FrameTooLarge | Error::CompactArray)
+          if skipped_errors < MAX_SKIPPED_ERRORS => skipped_errors += 1,
+        Err(err) => return Err(err),
+      }
+    }
+
+    Ok(readings)
   }
 }
 
+impl<R: Write> SmartMeter<R> {
+  /// Get mutable access to the underlying handle for writing, e.g. for request/response meters.
+  pub fn writer(&mut self) -> &mut R {
+    &mut self.reader
+  }
+}
+
+impl<R: Read + Write> SmartMeter<R> {
+  /// Write `request` and read the response.
+  ///
+  /// Buffered bytes are discarded before `request` is written and flushed,
+  /// so the result is the first complete reading received after the request.
+  /// Bytes still buffered by the operating system are indistinguishable from the response.
+  pub fn request_and_read(&mut self, request: &[u8]) -> Result<ObisMap, Error> {
+    self.decoder.clear();
+    self.reader.write_all(request)?;
+    self.reader.flush()?;
+    self.read_obis_map(None)
+  }
+}
+
+/// Iterate over all readings until the reader is exhausted.
+///
+/// Every error is yielded, but after [`Error::NoProgress`], i.e. once the reader keeps returning
+/// no bytes like at the end of a file, iteration ends. This also ends threads reading in the background,
+/// e.g. [`spawn`](SmartMeter::spawn) or [`merge`]. Use [`reopen`](SmartMeter::reopen) to continue afterwards.
 impl<R: Read> Iterator for SmartMeter<R> {
   type Item = Result<ObisMap, Error>;
 
   /// Get the next reading.
   fn next(&mut self) -> Option<Self::Item> {
-    let mut bytes_needed = 0;
-    let mut telegrams_needed = 1;
-
-    'outer: loop {
-      match (&mut self.reader).take(bytes_needed as u64).read_to_end(&mut self.buffer) {
-        Ok(_) => (),
-        Err(err) => return Some(Err(Error::Io(err))),
-      }
-
-      let mut telegrams = Vec::new();
-
-      let mut buffer = self.buffer.as_slice();
-      let mut telegram_1_len = 0;
-      let mut telegrams_len = 0;
-
-      for i in 0..telegrams_needed {
-        match Telegram::parse(buffer) {
-          Ok((next_buffer, telegram)) => {
-            let telegram_len = buffer.len() - next_buffer.len();
-            if i == 0 {
-              telegram_1_len = telegram_len;
-            }
-            telegrams_len += telegram_len;
-            buffer = next_buffer;
-            telegrams.push(telegram);
-          },
-          Err(MbusError::Incomplete(n)) => {
-            bytes_needed = n.map(|b| b.get()).unwrap_or(1);
-            continue 'outer
-          },
-          Err(MbusError::InvalidStartCharacter) => {
-            self.buffer.remove(0);
-            bytes_needed = 0;
-            continue 'outer
-          }
-          Err(MbusError::InvalidFormat | MbusError::ChecksumMismatch) => {
-            // Input is invalid but not incomplete,
-            // so try advancing the buffer.
-            self.buffer.remove(0);
-            bytes_needed = 0;
-            continue 'outer
-          },
-        }
+    if self.exhausted {
+      return None
+    }
+
+    let result = self.read_obis_map(None);
+    self.exhausted = matches!(result, Err(Error::NoProgress));
+    Some(result)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::time::SystemTime;
+
+  use crate::fixture::{frames_with_key, FRAMES, KEY};
+
+  /// A reader repeating `bytes` forever, advancing `clock` by a second on every read.
+  struct Repeat {
+    bytes: &'static [u8],
+    position: usize,
+    clock: MockClock,
+  }
+
+  impl Repeat {
+    fn new(bytes: &'static [u8], clock: &MockClock) -> Self {
+      Self { bytes, position: 0, clock: clock.clone() }
+    }
+  }
+
+  impl Read for Repeat {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      self.clock.advance(Duration::from_secs(1));
+
+      let len = buf.len().min(self.bytes.len() - self.position);
+      buf[..len].copy_from_slice(&self.bytes[self.position..(self.position + len)]);
+      self.position = (self.position + len) % self.bytes.len();
+      Ok(len)
+    }
+  }
+
+  #[test]
+  fn reading_deadline() {
+    let clock = MockClock::new(SystemTime::UNIX_EPOCH);
+    let mut smart_meter = SmartMeter::new(Repeat::new(&[0x00], &clock), Dlms::new(KEY))
+      .with_clock(clock.clone())
+      .reading_deadline(Duration::from_secs(10));
+
+    assert!(matches!(smart_meter.read_reading(), Err(Error::Deadline)));
+    assert_eq!(smart_meter.stats().bytes_read(), 11);
+  }
+
+  #[test]
+  fn soak() {
+    let clock = MockClock::new(SystemTime::UNIX_EPOCH);
+    let smart_meter = SmartMeter::new(Repeat::new(FRAMES, &clock), Dlms::new(KEY)).with_clock(clock.clone());
+
+    let report = smart_meter.soak_for(Duration::from_secs(60));
+    assert!(report.is_healthy());
+    assert!(report.readings > 0);
+  }
+
+  #[test]
+  fn validate_key() {
+    let timeout = Duration::from_secs(1);
+    assert!(SmartMeter::validate_key(io::Cursor::new(FRAMES), KEY, timeout).unwrap());
+    assert!(!SmartMeter::validate_key(io::Cursor::new(FRAMES), [0; 16], timeout).unwrap());
+  }
+
+  /// A reader returning at most one byte per read, and no bytes at all on every other read.
+  struct Trickle {
+    bytes: &'static [u8],
+    reads: usize,
+  }
+
+  impl Read for Trickle {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      self.reads += 1;
+
+      if self.reads.is_multiple_of(2) || buf.is_empty() {
+        return Ok(0)
       }
-      bytes_needed = 0;
 
-      match self.dlms.decrypt(&telegrams) {
-        Ok((_, obis_map)) => {
-          self.buffer.drain(0..telegrams_len);
-          return Some(Ok(obis_map))
-        },
-        Err(DlmsError::Incomplete(n)) => {
-          telegrams_needed += n.map(|t| t.get()).unwrap_or(1);
-        },
-        Err(DlmsError::InvalidFormat | DlmsError::ChecksumMismatch) => {
-          // Other error, continue with next telegram.
-          self.buffer.drain(0..telegram_1_len);
-          telegrams_needed = 1;
-          continue
-        },
-        Err(DlmsError::DecryptionFailed) => {
-          return Some(Err(Error::DecryptionFailed))
+      match self.bytes.split_first() {
+        Some((&byte, bytes)) => {
+          buf[0] = byte;
+          self.bytes = bytes;
+          Ok(1)
         },
+        None => Ok(0),
       }
     }
   }
+
+  #[test]
+  fn trickle() {
+    let mut smart_meter = SmartMeter::new(Trickle { bytes: FRAMES, reads: 0 }, Dlms::new(KEY));
+
+    assert_eq!(smart_meter.read_reading().unwrap().len(), 15);
+    assert!(matches!(smart_meter.read_reading(), Err(Error::NoProgress)));
+  }
+
+  #[test]
+  fn iterator_ends_at_eof() {
+    let smart_meter = SmartMeter::new(io::Cursor::new([FRAMES, &FRAMES[..100]].concat()), Dlms::new(KEY));
+
+    let items = smart_meter.collect::<Vec<_>>();
+    assert!(matches!(items.as_slice(), [Ok(_), Err(Error::NoProgress)]), "{:?}", items);
+  }
+
+  #[test]
+  fn skip_frames() {
+    let mut smart_meter = SmartMeter::new(io::Cursor::new([FRAMES, FRAMES].concat()), Dlms::new(KEY));
+
+    smart_meter.skip_frames(2).unwrap();
+    assert_eq!(smart_meter.stats().skipped_frames(), 2);
+    assert_eq!(smart_meter.read_reading().unwrap().len(), 15);
+    assert!(matches!(smart_meter.skip_frames(1), Err(Error::NoProgress)));
+  }
+
+  /// A reader counting the calls to `read`.
+  struct CountReads<R> {
+    reader: R,
+    reads: usize,
+  }
+
+  impl<R: Read> Read for CountReads<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      self.reads += 1;
+      self.reader.read(buf)
+    }
+  }
+
+  #[test]
+  fn read_calls() {
+    let mut smart_meter = SmartMeter::new(CountReads { reader: io::Cursor::new(FRAMES), reads: 0 }, Dlms::new(KEY));
+
+    assert_eq!(smart_meter.read_reading().unwrap().len(), 15);
+    // The start character, the length and rest of the header, and the rest of the frame, for each of the two frames.
+    assert_eq!(smart_meter.reader().reads, 6);
+  }
+
+  #[test]
+  fn set_dlms() {
+    let other_key = [0x01; 16];
+    let bytes = [FRAMES, &frames_with_key(other_key)].concat();
+    let mut smart_meter = SmartMeter::new(io::Cursor::new(bytes), Dlms::new(KEY)).replay_window(0);
+
+    assert_eq!(smart_meter.read_reading().unwrap().len(), 15);
+    smart_meter.set_dlms(Dlms::new(other_key));
+    assert_eq!(smart_meter.read_reading().unwrap().len(), 15);
+  }
 }
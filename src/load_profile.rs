@@ -0,0 +1,116 @@
+use dlms_cosem::DateTime;
+
+use crate::{DataTree, Error};
+
+/// A single interval of a load profile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProfileEntry {
+  /// The end of the interval, if the first captured value is a date-time.
+  pub timestamp: Option<DateTime>,
+  /// The captured values, excluding the timestamp.
+  pub values: Vec<DataTree>,
+}
+
+/// Find the profile buffer, i.e. the first list of lists starting with a date-time.
+fn find_buffer(tree: &DataTree) -> Option<&[DataTree]> {
+  let items = match tree {
+    DataTree::List(items) => items,
+    _ => return None,
+  };
+
+  let is_buffer = !items.is_empty() && items.iter().all(|item| match item {
+    DataTree::List(entry) => entry.first().and_then(DataTree::as_date_time).is_some(),
+    _ => false,
+  });
+
+  if is_buffer {
+    return Some(items)
+  }
+
+  items.iter().find_map(find_buffer)
+}
+
+/// Decode the load profile, e.g. 1-0:99.1.0, contained in a plain data-notification APDU.
+///
+/// The profile buffer is an array of entries, each a structure starting with the clock
+/// followed by the captured values. Returns [`Error::InvalidFormat`] if no buffer is found.
+pub fn parse_load_profile(apdu: &[u8]) -> Result<Vec<ProfileEntry>, Error> {
+  let tree = DataTree::from_apdu(apdu)?;
+  let buffer = find_buffer(&tree).ok_or(Error::InvalidFormat)?;
+
+  Ok(buffer.iter().filter_map(|entry| match entry {
+    DataTree::List(values) => {
+      let (timestamp, values) = values.split_first()?;
+      Some(ProfileEntry { timestamp: timestamp.as_date_time(), values: values.to_vec() })
+    },
+    _ => None,
+  }).collect())
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::fixture::notification_body;
+
+  /// Encode a load profile entry with the given hour and value.
+  fn entry(hour: u8, value: u8) -> Vec<u8> {
+    let mut entry = vec![0x02, 0x02, 0x09, 0x0c, 0x07, 0xe5, 0x09, 0x0b, 0x06, hour, 0x00, 0x00, 0x00, 0xff, 0x88, 0x80];
+    entry.extend([0x12, 0x00, value]);
+    entry
+  }
+
+  fn buffer(entries: &[Vec<u8>]) -> Vec<u8> {
+    [vec![0x01, entries.len() as u8], entries.concat()].concat()
+  }
+
+  fn timestamp(hour: u8) -> Option<DateTime> {
+    DataTree::Bytes(vec![0x07, 0xe5, 0x09, 0x0b, 0x06, hour, 0x00, 0x00, 0x00, 0xff, 0x88, 0x80]).as_date_time()
+  }
+
+  #[test]
+  fn several_intervals() {
+    let apdu = notification_body(&buffer(&[entry(1, 10), entry(2, 20), entry(3, 30)]));
+    let entries = parse_load_profile(&apdu).unwrap();
+
+    assert_eq!(entries, [(1, 10), (2, 20), (3, 30)].map(|(hour, value)| ProfileEntry {
+      timestamp: timestamp(hour),
+      values: vec![DataTree::Unsigned(value)],
+    }));
+  }
+
+  #[test]
+  fn nested_buffer() {
+    // Code, buffer and capture period.
+    let mut body = vec![0x02, 0x03, 0x09, 0x06, 0x01, 0x00, 0x63, 0x01, 0x00, 0xff];
+    body.extend(buffer(&[entry(1, 10), entry(2, 20)]));
+    body.extend([0x06, 0x00, 0x00, 0x03, 0x84]);
+
+    let entries = parse_load_profile(&notification_body(&body)).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[1].timestamp, timestamp(2));
+  }
+
+  #[test]
+  fn entry_without_date_time() {
+    // A list with an entry not starting with a date-time is not a buffer, so the search continues.
+    let mut body = vec![0x02, 0x02, 0x01, 0x02];
+    body.extend(entry(1, 10));
+    body.extend([0x02, 0x02, 0x12, 0x00, 0x01, 0x12, 0x00, 0x02]);
+    body.extend(buffer(&[entry(3, 30)]));
+
+    let entries = parse_load_profile(&notification_body(&body)).unwrap();
+    assert_eq!(entries, [ProfileEntry { timestamp: timestamp(3), values: vec![DataTree::Unsigned(30)] }]);
+
+    let body = [vec![0x01, 0x02], entry(1, 10), vec![0x02, 0x02, 0x12, 0x00, 0x01, 0x12, 0x00, 0x02]].concat();
+    assert!(matches!(parse_load_profile(&notification_body(&body)), Err(Error::InvalidFormat)));
+  }
+
+  #[test]
+  fn no_buffer() {
+    let body = [0x02, 0x02, 0x12, 0x00, 0x01, 0x12, 0x00, 0x02];
+    assert!(matches!(parse_load_profile(&notification_body(&body)), Err(Error::InvalidFormat)));
+    assert!(matches!(parse_load_profile(&notification_body(&[0x01, 0x00])), Err(Error::InvalidFormat)));
+    assert!(matches!(parse_load_profile(&[0x0f, 0x00]), Err(Error::InvalidFormat)));
+  }
+}
@@ -0,0 +1,136 @@
+use std::io::Read;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread;
+
+use dlms_cosem::ObisMap;
+
+use crate::{Error, SmartMeter};
+
+/// The source of an item yielded by [`Merge`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Source {
+  /// The first meter passed to [`merge`].
+  A,
+  /// The second meter passed to [`merge`].
+  B,
+}
+
+/// Iterator returned by [`merge`].
+#[derive(Debug)]
+pub struct Merge {
+  rx: Receiver<(Source, Result<ObisMap, Error>)>,
+}
+
+/// Merge the readings of two meters into one stream.
+///
+/// Each meter is read on its own thread and items are yielded in the order they arrive,
+/// tagged with their [`Source`]. An error from one meter does not stop the other.
+/// A thread blocked in a read only notices the [`Merge`] being dropped after its next item.
+/// The iterator ends once both readers are exhausted, i.e. after both sent [`Error::NoProgress`].
+///
+/// Items are sent through a channel holding up to `capacity` items, so a slow consumer blocks
+/// the readers instead of buffering without bound, see [`SmartMeter::spawn`].
+pub fn merge<A, B>(a: SmartMeter<A>, b: SmartMeter<B>, capacity: usize) -> Merge
+where
+  A: Read + Send + 'static,
+  B: Read + Send + 'static,
+{
+  let (tx, rx) = mpsc::sync_channel(capacity);
+
+  spawn(Source::A, a, tx.clone());
+  spawn(Source::B, b, tx);
+
+  Merge { rx }
+}
+
+fn spawn<T, R>(tag: T, smart_meter: SmartMeter<R>, tx: SyncSender<(T, Result<ObisMap, Error>)>)
+where
+  T: Copy + Send + 'static,
+  R: Read + Send + 'static,
+{
+  thread::spawn(move || {
+    for item in smart_meter {
+      if tx.send((tag, item)).is_err() {
+        break
+      }
+    }
+  });
+}
+
+impl Iterator for Merge {
+  type Item = (Source, Result<ObisMap, Error>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.rx.recv().ok()
+  }
+}
+
+/// Readings of any number of meters merged into one stream, see [`merge`] for two meters.
+///
+/// Each meter is read on its own thread and items are tagged with the index of their meter.
+/// Errors are isolated per meter, so one failing port does not stop the others.
+/// The iterator ends once all readers are exhausted, see [`merge`], also for `capacity`.
+#[derive(Debug)]
+pub struct MultiMeter {
+  rx: Receiver<(usize, Result<ObisMap, Error>)>,
+}
+
+impl MultiMeter {
+  /// Start reading all given meters.
+  pub fn new<R: Read + Send + 'static>(meters: impl IntoIterator<Item = SmartMeter<R>>, capacity: usize) -> Self {
+    let (tx, rx) = mpsc::sync_channel(capacity);
+
+    for (i, smart_meter) in meters.into_iter().enumerate() {
+      spawn(i, smart_meter, tx.clone());
+    }
+
+    Self { rx }
+  }
+}
+
+impl Iterator for MultiMeter {
+  type Item = (usize, Result<ObisMap, Error>);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.rx.recv().ok()
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::io::Cursor;
+
+  use dlms_cosem::Dlms;
+
+  use crate::fixture::{FRAMES, KEY};
+
+  #[test]
+  fn ends_when_exhausted() {
+    let a = SmartMeter::new(Cursor::new(FRAMES), Dlms::new(KEY));
+    let b = SmartMeter::new(Cursor::new([FRAMES, FRAMES].concat()), Dlms::new(KEY));
+
+    let items = merge(a, b, 1).collect::<Vec<_>>();
+    let count = |source, ok: bool| items.iter().filter(|(s, item)| *s == source && item.is_ok() == ok).count();
+
+    assert_eq!((count(Source::A, true), count(Source::A, false)), (1, 1));
+    assert_eq!((count(Source::B, true), count(Source::B, false)), (2, 1));
+  }
+
+  #[test]
+  fn bounded() {
+    let a = SmartMeter::new(Cursor::new([FRAMES, FRAMES, FRAMES].concat()), Dlms::new(KEY));
+    let b = SmartMeter::new(Cursor::new(FRAMES), Dlms::new(KEY));
+
+    // A rendezvous channel still delivers every item.
+    let items = merge(a, b, 0).collect::<Vec<_>>();
+    assert_eq!(items.iter().filter(|(_, item)| item.is_ok()).count(), 4);
+  }
+
+  #[test]
+  fn multi_meter_ends_when_exhausted() {
+    let meters = (0..3).map(|_| SmartMeter::new(Cursor::new(FRAMES), Dlms::new(KEY)));
+    assert_eq!(MultiMeter::new(meters, 0).filter(|(_, item)| item.is_ok()).count(), 3);
+  }
+}
@@ -0,0 +1,70 @@
+use std::fmt;
+
+use dlms_cosem::ObisCode;
+
+/// An OBIS code usable as `HashMap` key, since `ObisCode` does not implement `Hash`.
+///
+/// ```
+/// use std::collections::HashMap;
+///
+/// use dlms_cosem::ObisCode;
+/// use smart_meter::ObisKey;
+///
+/// let mut values = HashMap::new();
+/// values.insert(ObisKey::from(ObisCode::new(1, 0, 1, 8, 0, 255)), 1234);
+///
+/// assert_eq!(values.get(&ObisCode::new(1, 0, 1, 8, 0, 255).into()), Some(&1234));
+/// assert_eq!(values.get(&ObisKey::new(1, 0, 2, 8, 0, 255)), None);
+/// assert_eq!(ObisCode::from(ObisKey::new(1, 0, 1, 8, 0, 255)), ObisCode::new(1, 0, 1, 8, 0, 255));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ObisKey([u8; 6]);
+
+impl ObisKey {
+  pub const fn new(a: u8, b: u8, c: u8, d: u8, e: u8, f: u8) -> Self {
+    Self([a, b, c, d, e, f])
+  }
+
+  /// The value groups A to F.
+  pub const fn groups(&self) -> [u8; 6] {
+    self.0
+  }
+}
+
+impl From<&ObisCode> for ObisKey {
+  fn from(code: &ObisCode) -> Self {
+    // `ObisCode` only exposes its groups through `Display`.
+    let code = code.to_string();
+    let mut groups = [0; 6];
+    for (group, n) in groups.iter_mut().zip(code.split(['-', ':', '.', '*'])) {
+      *group = n.parse().unwrap_or_default();
+    }
+    Self(groups)
+  }
+}
+
+impl From<ObisCode> for ObisKey {
+  fn from(code: ObisCode) -> Self {
+    Self::from(&code)
+  }
+}
+
+impl From<ObisKey> for ObisCode {
+  fn from(key: ObisKey) -> Self {
+    let [a, b, c, d, e, f] = key.0;
+    Self::new(a, b, c, d, e, f)
+  }
+}
+
+impl fmt::Display for ObisKey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let [a, b, c, d, e, g] = self.0;
+    write!(f, "{}-{}:{}.{}.{}*{}", a, b, c, d, e, g)
+  }
+}
+
+impl fmt::Debug for ObisKey {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "ObisKey(\"{}\")", self)
+  }
+}
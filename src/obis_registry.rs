@@ -0,0 +1,97 @@
+//! Human-readable descriptions for standardized OBIS codes.
+
+use dlms_cosem::{ObisCode, Unit};
+
+/// Description of a standardized OBIS code.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ObisInfo {
+  name: &'static str,
+  description: &'static str,
+  unit: Option<Unit>,
+}
+
+impl ObisInfo {
+  const fn new(name: &'static str, description: &'static str, unit: Option<Unit>) -> Self {
+    Self { name, description, unit }
+  }
+
+  /// A short name, e.g. for use as a label.
+  pub fn name(&self) -> &'static str {
+    self.name
+  }
+
+  /// A longer description of the measured quantity.
+  pub fn description(&self) -> &'static str {
+    self.description
+  }
+
+  /// The unit this value is typically reported in.
+  pub fn unit(&self) -> Option<&Unit> {
+    self.unit.as_ref()
+  }
+}
+
+macro_rules! registry {
+  ($([$a:literal, $b:literal, $c:literal, $d:literal, $e:literal] => $name:literal, $description:literal, $unit:expr;)*) => {
+    const REGISTRY: &[(ObisCode, ObisInfo)] = &[
+      $((ObisCode::new($a, $b, $c, $d, $e, 255), ObisInfo::new($name, $description, $unit)),)*
+    ];
+  };
+}
+
+registry! {
+  [0, 0, 1, 0, 0] => "Clock", "Current date and time of the meter", None;
+  [0, 0, 42, 0, 0] => "Logical Device Name", "COSEM logical device name", None;
+  [0, 0, 96, 1, 0] => "Equipment Identifier", "Serial number of the meter", None;
+  [0, 0, 96, 3, 10] => "Disconnect Control State", "State of the breaker/disconnector", None;
+  [0, 0, 96, 7, 9] => "Long Power Failures", "Number of long power failures in any phase", None;
+  [0, 0, 96, 7, 21] => "Power Failures", "Number of power failures in any phase", None;
+  [0, 0, 96, 13, 0] => "Consumer Message", "Text message sent to the consumer", None;
+  [0, 0, 96, 14, 0] => "Active Tariff", "Currently active tariff", None;
+  [1, 0, 0, 2, 0] => "Firmware Version", "Active firmware identifier", None;
+  [1, 0, 1, 6, 0] => "Maximum Demand Import", "Maximum demand of active power import (+P)", Some(Unit::Watt);
+  [1, 0, 1, 7, 0] => "Active Power Import", "Instantaneous active power import (+P)", Some(Unit::Watt);
+  [1, 0, 1, 8, 0] => "Active Energy Import", "Total active energy import (+A)", Some(Unit::WattHour);
+  [1, 0, 1, 8, 1] => "Active Energy Import Tariff 1", "Active energy import (+A) in tariff 1", Some(Unit::WattHour);
+  [1, 0, 1, 8, 2] => "Active Energy Import Tariff 2", "Active energy import (+A) in tariff 2", Some(Unit::WattHour);
+  [1, 0, 2, 6, 0] => "Maximum Demand Export", "Maximum demand of active power export (-P)", Some(Unit::Watt);
+  [1, 0, 2, 7, 0] => "Active Power Export", "Instantaneous active power export (-P)", Some(Unit::Watt);
+  [1, 0, 2, 8, 0] => "Active Energy Export", "Total active energy export (-A)", Some(Unit::WattHour);
+  [1, 0, 2, 8, 1] => "Active Energy Export Tariff 1", "Active energy export (-A) in tariff 1", Some(Unit::WattHour);
+  [1, 0, 2, 8, 2] => "Active Energy Export Tariff 2", "Active energy export (-A) in tariff 2", Some(Unit::WattHour);
+  [1, 0, 3, 7, 0] => "Reactive Power Import", "Instantaneous reactive power import (+Q)", Some(Unit::Var);
+  [1, 0, 3, 8, 0] => "Reactive Energy Import", "Total reactive energy import (+R)", Some(Unit::VarHour);
+  [1, 0, 4, 7, 0] => "Reactive Power Export", "Instantaneous reactive power export (-Q)", Some(Unit::Var);
+  [1, 0, 4, 8, 0] => "Reactive Energy Export", "Total reactive energy export (-R)", Some(Unit::VarHour);
+  [1, 0, 9, 7, 0] => "Apparent Power Import", "Instantaneous apparent power import (+S)", Some(Unit::VoltAmpere);
+  [1, 0, 13, 7, 0] => "Power Factor", "Instantaneous power factor", None;
+  [1, 0, 14, 7, 0] => "Frequency", "Instantaneous supply frequency", Some(Unit::Hertz);
+  [1, 0, 21, 7, 0] => "Active Power Import L1", "Instantaneous active power import (+P) in phase L1", Some(Unit::Watt);
+  [1, 0, 22, 7, 0] => "Active Power Export L1", "Instantaneous active power export (-P) in phase L1", Some(Unit::Watt);
+  [1, 0, 31, 7, 0] => "Current L1", "Instantaneous current in phase L1", Some(Unit::Ampere);
+  [1, 0, 31, 7, 124] => "Current THD L1", "Total harmonic distortion of the current in phase L1", Some(Unit::Percent);
+  [1, 0, 32, 7, 0] => "Voltage L1", "Instantaneous voltage in phase L1", Some(Unit::Volt);
+  [1, 0, 32, 7, 124] => "Voltage THD L1", "Total harmonic distortion of the voltage in phase L1", Some(Unit::Percent);
+  [1, 0, 33, 7, 0] => "Power Factor L1", "Instantaneous power factor in phase L1", None;
+  [1, 0, 41, 7, 0] => "Active Power Import L2", "Instantaneous active power import (+P) in phase L2", Some(Unit::Watt);
+  [1, 0, 42, 7, 0] => "Active Power Export L2", "Instantaneous active power export (-P) in phase L2", Some(Unit::Watt);
+  [1, 0, 51, 7, 0] => "Current L2", "Instantaneous current in phase L2", Some(Unit::Ampere);
+  [1, 0, 51, 7, 124] => "Current THD L2", "Total harmonic distortion of the current in phase L2", Some(Unit::Percent);
+  [1, 0, 52, 7, 0] => "Voltage L2", "Instantaneous voltage in phase L2", Some(Unit::Volt);
+  [1, 0, 52, 7, 124] => "Voltage THD L2", "Total harmonic distortion of the voltage in phase L2", Some(Unit::Percent);
+  [1, 0, 53, 7, 0] => "Power Factor L2", "Instantaneous power factor in phase L2", None;
+  [1, 0, 61, 7, 0] => "Active Power Import L3", "Instantaneous active power import (+P) in phase L3", Some(Unit::Watt);
+  [1, 0, 62, 7, 0] => "Active Power Export L3", "Instantaneous active power export (-P) in phase L3", Some(Unit::Watt);
+  [1, 0, 71, 7, 0] => "Current L3", "Instantaneous current in phase L3", Some(Unit::Ampere);
+  [1, 0, 71, 7, 124] => "Current THD L3", "Total harmonic distortion of the current in phase L3", Some(Unit::Percent);
+  [1, 0, 72, 7, 0] => "Voltage L3", "Instantaneous voltage in phase L3", Some(Unit::Volt);
+  [1, 0, 72, 7, 124] => "Voltage THD L3", "Total harmonic distortion of the voltage in phase L3", Some(Unit::Percent);
+  [1, 0, 73, 7, 0] => "Power Factor L3", "Instantaneous power factor in phase L3", None;
+  [1, 0, 91, 7, 0] => "Current Neutral", "Instantaneous current in the neutral conductor", Some(Unit::Ampere);
+  [1, 0, 99, 1, 0] => "Load Profile", "Load profile with recording period 1", None;
+}
+
+/// Look up the description of a standardized OBIS code.
+pub fn describe(code: &ObisCode) -> Option<ObisInfo> {
+  REGISTRY.iter().find(|(c, _)| c == code).map(|(_, info)| *info)
+}
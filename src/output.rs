@@ -0,0 +1,256 @@
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{self, Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{CsvWriter, FanOut, JsonLinesWriter, PrometheusTextfile};
+
+/// A single output of an [`OutputConfig`].
+///
+/// MQTT is not supported, since there is no MQTT client among the dependencies of this crate,
+/// so configurations selecting it are rejected with an unknown variant error.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Output {
+  /// Newline-delimited JSON on standard output, see [`JsonLinesWriter`].
+  Stdout,
+  /// Newline-delimited JSON appended to the file at `path`, see [`JsonLinesWriter`].
+  Json { path: PathBuf },
+  /// CSV rows appended to the file at `path`, see [`CsvWriter`].
+  Csv { path: PathBuf },
+  /// The latest reading in the Prometheus text format in the file at `path`, see [`PrometheusTextfile`].
+  Prometheus { path: PathBuf },
+}
+
+impl Output {
+  fn path(&self) -> Option<&Path> {
+    match self {
+      Self::Stdout => None,
+      Self::Json { path } | Self::Csv { path } | Self::Prometheus { path } => Some(path),
+    }
+  }
+}
+
+/// Error returned if an [`OutputConfig`] is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputConfigError {
+  /// No output was selected.
+  Empty,
+  /// Standard output was selected more than once.
+  DuplicateStdout,
+  /// The same file was selected more than once, possibly by different outputs.
+  DuplicatePath(PathBuf),
+}
+
+impl fmt::Display for OutputConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Empty => write!(f, "no output selected"),
+      Self::DuplicateStdout => write!(f, "stdout selected more than once"),
+      Self::DuplicatePath(path) => write!(f, "file {} selected more than once", path.display()),
+    }
+  }
+}
+
+impl std::error::Error for OutputConfigError {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawOutputConfig {
+  outputs: Vec<Output>,
+}
+
+/// A validated selection of outputs, e.g. loaded from a configuration file.
+///
+/// Conflicting outputs are rejected while deserializing, see [`OutputConfigError`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawOutputConfig")]
+pub struct OutputConfig {
+  outputs: Vec<Output>,
+}
+
+impl OutputConfig {
+  /// Validate a selection of outputs.
+  ///
+  /// Paths are made absolute relative to the current directory before comparing them,
+  /// so `a.json` and `./a.json` are the same file. Symbolic links are not resolved.
+  pub fn new(outputs: Vec<Output>) -> Result<Self, OutputConfigError> {
+    if outputs.is_empty() {
+      return Err(OutputConfigError::Empty)
+    }
+
+    if outputs.iter().filter(|output| **output == Output::Stdout).count() > 1 {
+      return Err(OutputConfigError::DuplicateStdout)
+    }
+
+    let mut paths = Vec::new();
+    for path in outputs.iter().filter_map(Output::path) {
+      let absolute = path::absolute(path).unwrap_or_else(|_| path.to_owned());
+
+      if paths.contains(&absolute) {
+        return Err(OutputConfigError::DuplicatePath(path.to_owned()))
+      }
+
+      paths.push(absolute);
+    }
+
+    Ok(Self { outputs })
+  }
+
+  /// The selected outputs.
+  pub fn outputs(&self) -> &[Output] {
+    &self.outputs
+  }
+}
+
+impl TryFrom<RawOutputConfig> for OutputConfig {
+  type Error = OutputConfigError;
+
+  fn try_from(raw: RawOutputConfig) -> Result<Self, Self::Error> {
+    Self::new(raw.outputs)
+  }
+}
+
+fn append(path: &Path) -> io::Result<File> {
+  OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Construct a [`FanOut`] sink for all outputs in `config`.
+///
+/// Files are created if they do not exist yet.
+pub fn build_sinks(config: &OutputConfig) -> io::Result<FanOut> {
+  let mut fan_out = FanOut::default();
+
+  for output in &config.outputs {
+    fan_out = match output {
+      Output::Stdout => fan_out.with_sink(JsonLinesWriter::new(io::stdout())),
+      Output::Json { path } => fan_out.with_sink(JsonLinesWriter::new(append(path)?)),
+      Output::Csv { path } => fan_out.with_sink(CsvWriter::new(append(path)?)),
+      Output::Prometheus { path } => fan_out.with_sink(PrometheusTextfile::new(path)),
+    };
+  }
+
+  Ok(fan_out)
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::env;
+  use std::fs;
+  use std::process;
+
+  use crate::Sink;
+  use crate::fixture::{notification, obis_map};
+
+  fn json(path: &str) -> Output {
+    Output::Json { path: path.into() }
+  }
+
+  #[test]
+  fn empty() {
+    assert_eq!(OutputConfig::new(vec![]), Err(OutputConfigError::Empty));
+  }
+
+  #[test]
+  fn duplicate_stdout() {
+    assert_eq!(OutputConfig::new(vec![Output::Stdout, json("a.json"), Output::Stdout]), Err(OutputConfigError::DuplicateStdout));
+  }
+
+  #[test]
+  fn duplicate_path() {
+    assert_eq!(OutputConfig::new(vec![json("a.json"), json("a.json")]), Err(OutputConfigError::DuplicatePath("a.json".into())));
+    assert_eq!(OutputConfig::new(vec![json("a.json"), json("./a.json")]), Err(OutputConfigError::DuplicatePath("./a.json".into())));
+    assert_eq!(
+      OutputConfig::new(vec![json("a.json"), Output::Csv { path: "a.json".into() }]),
+      Err(OutputConfigError::DuplicatePath("a.json".into())),
+    );
+
+    let config = OutputConfig::new(vec![Output::Stdout, json("a.json"), json("b.json")]).unwrap();
+    assert_eq!(config.outputs().len(), 3);
+  }
+
+  #[test]
+  fn deserialize() {
+    let config: OutputConfig = serde_json::from_str(r#"{"outputs":[
+      {"type":"stdout"},
+      {"type":"json","path":"a.json"},
+      {"type":"csv","path":"a.csv"},
+      {"type":"prometheus","path":"a.prom"}
+    ]}"#).unwrap();
+
+    assert_eq!(config.outputs(), [
+      Output::Stdout,
+      json("a.json"),
+      Output::Csv { path: "a.csv".into() },
+      Output::Prometheus { path: "a.prom".into() },
+    ]);
+  }
+
+  #[test]
+  fn deserialize_invalid() {
+    let err = serde_json::from_str::<OutputConfig>(r#"{"outputs":[]}"#).unwrap_err();
+    assert_eq!(err.to_string(), "no output selected");
+
+    let err = serde_json::from_str::<OutputConfig>(r#"{"outputs":[{"type":"json","path":"a.json"},{"type":"json","path":"./a.json"}]}"#).unwrap_err();
+    assert_eq!(err.to_string(), "file ./a.json selected more than once");
+
+    let err = serde_json::from_str::<OutputConfig>(r#"{"outputs":[{"type":"mqtt","host":"localhost"}]}"#).unwrap_err();
+    assert!(err.to_string().starts_with("unknown variant `mqtt`"), "{}", err);
+
+    let err = serde_json::from_str::<OutputConfig>(r#"{"outputs":[{"type":"json","path":"a.json","pretty":true}]}"#).unwrap_err();
+    assert!(err.to_string().starts_with("unknown field `pretty`"), "{}", err);
+
+    let err = serde_json::from_str::<OutputConfig>(r#"{"outputs":[{"type":"stdout"}],"format":"json"}"#).unwrap_err();
+    assert!(err.to_string().starts_with("unknown field `format`"), "{}", err);
+  }
+
+  #[test]
+  fn round_trip() {
+    let config = OutputConfig::new(vec![
+      Output::Stdout,
+      json("a.json"),
+      Output::Csv { path: "a.csv".into() },
+      Output::Prometheus { path: "a.prom".into() },
+    ]).unwrap();
+
+    let serialized = serde_json::to_string(&config).unwrap();
+    assert_eq!(serde_json::from_str::<OutputConfig>(&serialized).unwrap(), config);
+  }
+
+  #[test]
+  fn sinks() {
+    let dir = env::temp_dir();
+    let json_path = dir.join(format!("smart_meter_output_{}.json", process::id()));
+    let csv_path = dir.join(format!("smart_meter_output_{}.csv", process::id()));
+    let prometheus_path = dir.join(format!("smart_meter_output_{}.prom", process::id()));
+
+    let config = OutputConfig::new(vec![
+      Output::Json { path: json_path.clone() },
+      Output::Csv { path: csv_path.clone() },
+      Output::Prometheus { path: prometheus_path.clone() },
+    ]).unwrap();
+
+    let reading = obis_map(&notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x04, 0xd2], Some((0, 30)))]));
+
+    let mut sinks = build_sinks(&config).unwrap();
+    sinks.consume(&reading).unwrap();
+    sinks.consume(&reading).unwrap();
+
+    let json = fs::read_to_string(&json_path).unwrap();
+    let csv = fs::read_to_string(&csv_path).unwrap();
+    let prometheus = fs::read_to_string(&prometheus_path).unwrap();
+    for path in [json_path, csv_path, prometheus_path] {
+      fs::remove_file(path).unwrap();
+    }
+
+    assert_eq!(json.lines().count(), 2);
+    assert!(json.lines().all(|line| line.ends_with(r#","1-0:1.8.0*255":{"value":1234,"unit":"Wh"}}"#)), "{}", json);
+    assert_eq!(csv.lines().count(), 2);
+    assert!(csv.lines().all(|line| line.ends_with(",1-0:1.8.0*255,1234,Wh")), "{}", csv);
+    assert_eq!(prometheus, crate::to_prometheus(&reading));
+  }
+}
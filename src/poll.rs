@@ -0,0 +1,57 @@
+use std::io::Read;
+
+use dlms_cosem::ObisMap;
+
+use crate::{Error, SmartMeter};
+
+/// The result of polling a non-blocking reader.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Poll<T> {
+  /// A value is available.
+  Ready(T),
+  /// More bytes are needed before a value is available.
+  Pending,
+}
+
+impl<T> Poll<T> {
+  /// Whether a value is available.
+  pub fn is_ready(&self) -> bool {
+    matches!(self, Self::Ready(_))
+  }
+
+  /// Whether more bytes are needed.
+  pub fn is_pending(&self) -> bool {
+    matches!(self, Self::Pending)
+  }
+
+  /// Convert into an `Option`, mapping `Pending` to `None`.
+  pub fn ready(self) -> Option<T> {
+    match self {
+      Self::Ready(value) => Some(value),
+      Self::Pending => None,
+    }
+  }
+}
+
+/// Iterator returned by [`SmartMeter::non_blocking`].
+///
+/// This never ends; `Pending` is yielded whenever no complete reading is available yet.
+#[derive(Debug)]
+pub struct NonBlocking<R> {
+  pub(crate) smart_meter: SmartMeter<R>,
+}
+
+impl<R> NonBlocking<R> {
+  /// Get back the underlying [`SmartMeter`].
+  pub fn into_inner(self) -> SmartMeter<R> {
+    self.smart_meter
+  }
+}
+
+impl<R: Read> Iterator for NonBlocking<R> {
+  type Item = Result<Poll<ObisMap>, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    Some(self.smart_meter.poll_obis_map())
+  }
+}
@@ -0,0 +1,105 @@
+use dlms_cosem::{Dlms, ObisCode, ObisMap};
+
+use crate::{convert::{self, Conversion}, SmartMeter, Transform};
+
+/// A ready-made configuration for a specific kind of meter.
+///
+/// A profile bundles the conversions needed to get meaningful values
+/// and the set of registers the meter is expected to report.
+#[derive(Debug, Clone)]
+pub struct Profile {
+  conversions: Vec<(ObisCode, Conversion)>,
+  registers: Vec<ObisCode>,
+}
+
+impl Profile {
+  /// Profile for the Kaifa MA309 as used by EVN and Kärnten Netz in Austria.
+  ///
+  /// The meter pushes an encrypted M-Bus frame every 5 seconds on its customer
+  /// interface, using 2400 baud, 8 data bits, even parity and 1 stop bit.
+  pub fn evn_kaifa() -> Self {
+    Self {
+      conversions: vec![
+        (ObisCode::new(0, 0, 1, 0, 0, 255), convert::date_time),
+        (ObisCode::new(0, 0, 42, 0, 0, 255), convert::utf8_string),
+        (ObisCode::new(0, 0, 96, 1, 0, 255), convert::utf8_string),
+      ],
+      registers: vec![
+        ObisCode::new(0, 0, 1, 0, 0, 255),
+        ObisCode::new(0, 0, 42, 0, 0, 255),
+        ObisCode::new(0, 0, 96, 1, 0, 255),
+        ObisCode::new(1, 0, 32, 7, 0, 255),
+        ObisCode::new(1, 0, 52, 7, 0, 255),
+        ObisCode::new(1, 0, 72, 7, 0, 255),
+        ObisCode::new(1, 0, 31, 7, 0, 255),
+        ObisCode::new(1, 0, 51, 7, 0, 255),
+        ObisCode::new(1, 0, 71, 7, 0, 255),
+        ObisCode::new(1, 0, 1, 7, 0, 255),
+        ObisCode::new(1, 0, 2, 7, 0, 255),
+        ObisCode::new(1, 0, 1, 8, 0, 255),
+        ObisCode::new(1, 0, 2, 8, 0, 255),
+        ObisCode::new(1, 0, 3, 8, 0, 255),
+        ObisCode::new(1, 0, 4, 8, 0, 255),
+      ],
+    }
+  }
+
+  /// The conversions applied to each reading.
+  pub fn conversions(&self) -> &[(ObisCode, Conversion)] {
+    &self.conversions
+  }
+
+  /// The registers the meter is expected to report.
+  pub fn registers(&self) -> &[ObisCode] {
+    &self.registers
+  }
+
+  /// Get the expected registers which are missing from a reading.
+  pub fn missing_registers<'a>(&'a self, obis_map: &'a ObisMap) -> impl Iterator<Item = &'a ObisCode> {
+    self.registers.iter().filter(move |code| !obis_map.contains_key(code))
+  }
+
+  /// Create a `SmartMeter` which applies this profile's conversions to every reading.
+  pub fn smart_meter<R>(&self, reader: R, key: [u8; 16]) -> SmartMeter<R> {
+    SmartMeter::new(reader, Dlms::new(key)).with_transform(self.clone())
+  }
+}
+
+impl Transform for Profile {
+  fn apply(&mut self, obis_map: &mut ObisMap) {
+    for (code, f) in &self.conversions {
+      obis_map.convert(code, f);
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::io;
+
+  use dlms_cosem::Data;
+
+  use super::*;
+  use crate::fixture::{FRAMES, KEY};
+
+  #[test]
+  fn evn_kaifa() {
+    let profile = Profile::evn_kaifa();
+    let obis_map = profile.smart_meter(io::Cursor::new(FRAMES), KEY).next().unwrap().unwrap();
+
+    assert_eq!(profile.missing_registers(&obis_map).count(), 0);
+    assert_eq!(obis_map.len(), profile.registers().len());
+    assert!(matches!(obis_map.get(&ObisCode::new(0, 0, 1, 0, 0, 255)).map(|r| r.value()), Some(Data::DateTime(_))));
+    assert!(matches!(obis_map.get(&ObisCode::new(0, 0, 42, 0, 0, 255)).map(|r| r.value()), Some(Data::Utf8String(_))));
+    assert!(matches!(obis_map.get(&ObisCode::new(0, 0, 96, 1, 0, 255)).map(|r| r.value()), Some(Data::Utf8String(_))));
+  }
+
+  #[test]
+  fn missing_registers() {
+    let profile = Profile::evn_kaifa();
+    let obis_map = crate::fixture::obis_map(&crate::fixture::notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0, 0, 0, 1], Some((0, 30)))]));
+
+    assert_eq!(profile.missing_registers(&obis_map).count(), profile.registers().len() - 1);
+    assert!(profile.missing_registers(&obis_map).all(|code| *code != ObisCode::new(1, 0, 1, 8, 0, 255)));
+  }
+}
@@ -0,0 +1,93 @@
+use std::collections::BTreeMap;
+
+use dlms_cosem::{Data, ObisCode, ObisMap, Unit};
+
+use crate::{Sink, SinkError};
+
+/// What a [`CodeProfiler`] has seen of a single code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeInfo {
+  /// The unit of the last value.
+  pub unit: Option<Unit>,
+  /// The last value.
+  pub sample: Data,
+  /// The number of readings containing the code.
+  pub count: u64,
+}
+
+/// Collects all codes emitted by a meter, e.g. to discover which registers it exposes.
+#[derive(Debug, Clone, Default)]
+pub struct CodeProfiler {
+  readings: u64,
+  codes: BTreeMap<ObisCode, CodeInfo>,
+}
+
+impl CodeProfiler {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Add the codes of a reading.
+  pub fn update(&mut self, obis_map: &ObisMap) {
+    self.readings += 1;
+
+    for (code, reg) in obis_map.iter() {
+      let info = self.codes.entry(code.clone()).or_insert_with(|| CodeInfo { unit: None, sample: Data::Null, count: 0 });
+      info.unit = reg.unit().copied();
+      info.sample = reg.value().clone();
+      info.count += 1;
+    }
+  }
+
+  /// The number of readings seen so far.
+  pub fn readings(&self) -> u64 {
+    self.readings
+  }
+
+  /// All codes seen so far.
+  pub fn snapshot(&self) -> BTreeMap<ObisCode, CodeInfo> {
+    self.codes.clone()
+  }
+}
+
+impl Sink for CodeProfiler {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    self.update(reading);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::fixture::{notification, obis_map};
+
+  const ENERGY: ObisCode = ObisCode::new(1, 0, 1, 8, 0, 255);
+  const POWER: ObisCode = ObisCode::new(1, 0, 1, 7, 0, 255);
+  const VOLTAGE: ObisCode = ObisCode::new(1, 0, 32, 7, 0, 255);
+
+  #[test]
+  fn profile() {
+    let mut profiler = CodeProfiler::new();
+
+    profiler.update(&obis_map(&notification(&[
+      ([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x01], Some((0, 30))),
+      ([1, 0, 1, 7, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x02], Some((0, 27))),
+    ])));
+    profiler.consume(&obis_map(&notification(&[
+      ([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x03], None),
+      ([1, 0, 32, 7, 0, 255], &[0x12, 0x00, 0xe6], Some((0, 35))),
+    ]))).unwrap();
+
+    assert_eq!(profiler.readings(), 2);
+
+    let snapshot = profiler.snapshot();
+    assert_eq!(snapshot.keys().collect::<Vec<_>>(), [&POWER, &ENERGY, &VOLTAGE]);
+
+    // The last sample and unit are kept, scaled values are floats.
+    assert_eq!(snapshot[&ENERGY], CodeInfo { unit: None, sample: Data::DoubleLongUnsigned(3), count: 2 });
+    assert_eq!(snapshot[&POWER], CodeInfo { unit: Some(Unit::Watt), sample: Data::Float64(2.0), count: 1 });
+    assert_eq!(snapshot[&VOLTAGE], CodeInfo { unit: Some(Unit::Volt), sample: Data::Float32(230.0), count: 1 });
+  }
+}
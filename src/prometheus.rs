@@ -0,0 +1,117 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use dlms_cosem::{ObisCode, ObisMap};
+
+use crate::{as_f64, obis_registry, Sink, SinkError};
+
+/// Turn a name into a valid metric name, replacing everything but ASCII alphanumerics with `_`.
+fn sanitize(s: &str) -> String {
+  s.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect()
+}
+
+fn metric_name(code: &ObisCode) -> String {
+  match obis_registry::describe(code) {
+    Some(info) => format!("smart_meter_{}", sanitize(info.name())),
+    None => format!("smart_meter_obis_{}", sanitize(&code.to_string())),
+  }
+}
+
+/// Convert a reading to the Prometheus text exposition format.
+///
+/// Metric names are derived from the OBIS registry, e.g. `smart_meter_active_energy_import`,
+/// or from the code itself for unknown registers. Non-numeric registers are omitted.
+pub fn to_prometheus(obis_map: &ObisMap) -> String {
+  let mut metrics = String::new();
+
+  for (code, reg) in obis_map.iter() {
+    let value = match as_f64(reg.value()) {
+      Some(value) => value,
+      None => continue,
+    };
+
+    let name = metric_name(code);
+    let unit = reg.unit().and_then(|u| u.as_str()).unwrap_or("");
+
+    if let Some(info) = obis_registry::describe(code) {
+      writeln!(metrics, "# HELP {} {}", name, info.description()).unwrap();
+    }
+    writeln!(metrics, "# TYPE {} gauge", name).unwrap();
+    writeln!(metrics, r#"{}{{obis="{}",unit="{}"}} {}"#, name, code, unit, value).unwrap();
+  }
+
+  metrics
+}
+
+/// Writes the latest reading to a file for the node exporter's textfile collector, see [`to_prometheus`].
+///
+/// The file is replaced on every reading by writing to a temporary file next to it and renaming it,
+/// so the collector never sees a partially written file.
+#[derive(Debug)]
+pub struct PrometheusTextfile {
+  path: PathBuf,
+}
+
+impl PrometheusTextfile {
+  pub fn new(path: impl Into<PathBuf>) -> Self {
+    Self { path: path.into() }
+  }
+
+  /// Replace the file with the given reading.
+  pub fn write(&mut self, obis_map: &ObisMap) -> io::Result<()> {
+    let mut tmp = self.path.clone().into_os_string();
+    tmp.push(".tmp");
+
+    fs::write(&tmp, to_prometheus(obis_map))?;
+    fs::rename(&tmp, &self.path)
+  }
+}
+
+impl Sink for PrometheusTextfile {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    Ok(self.write(reading)?)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::env;
+  use std::process;
+
+  use crate::fixture::{notification, obis_map};
+
+  #[test]
+  fn metrics() {
+    let obis_map = obis_map(&notification(&[
+      ([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x04, 0xd2], Some((0, 30))),
+      ([1, 0, 99, 8, 0, 255], &[0x12, 0x00, 0x07], None),
+      ([0, 0, 96, 1, 0, 255], &[0x09, 0x01, b'a'], None),
+    ]));
+
+    let metrics = to_prometheus(&obis_map);
+    assert!(metrics.contains("# TYPE smart_meter_active_energy_import gauge\n"));
+    assert!(metrics.contains("smart_meter_active_energy_import{obis=\"1-0:1.8.0*255\",unit=\"Wh\"} 1234\n"));
+    assert!(metrics.contains("smart_meter_obis_1_0_99_8_0_255{obis=\"1-0:99.8.0*255\",unit=\"\"} 7\n"));
+    assert!(!metrics.contains("96.1.0"));
+  }
+
+  #[test]
+  fn textfile() {
+    let path = env::temp_dir().join(format!("smart_meter_textfile_{}.prom", process::id()));
+    let mut textfile = PrometheusTextfile::new(&path);
+
+    let first = obis_map(&notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x01], Some((0, 30)))]));
+    let second = obis_map(&notification(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x02], Some((0, 30)))]));
+
+    textfile.write(&first).unwrap();
+    textfile.write(&second).unwrap();
+
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    assert_eq!(contents, to_prometheus(&second));
+  }
+}
@@ -0,0 +1,145 @@
+//! Protocol Buffers messages for readings, see `proto/smart_meter.proto`.
+
+use dlms_cosem::{Data, ObisCode as DlmsObisCode};
+
+use crate::{format_value, ObisKey, Reading};
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ObisCode {
+  #[prost(uint32, tag = "1")]
+  pub a: u32,
+  #[prost(uint32, tag = "2")]
+  pub b: u32,
+  #[prost(uint32, tag = "3")]
+  pub c: u32,
+  #[prost(uint32, tag = "4")]
+  pub d: u32,
+  #[prost(uint32, tag = "5")]
+  pub e: u32,
+  #[prost(uint32, tag = "6")]
+  pub f: u32,
+}
+
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum Value {
+  #[prost(sint64, tag = "2")]
+  Integer(i64),
+  #[prost(uint64, tag = "3")]
+  Unsigned(u64),
+  #[prost(double, tag = "4")]
+  Float(f64),
+  #[prost(string, tag = "5")]
+  Text(String),
+  #[prost(bytes = "vec", tag = "6")]
+  Octets(Vec<u8>),
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Register {
+  #[prost(message, optional, tag = "1")]
+  pub code: Option<ObisCode>,
+  #[prost(oneof = "Value", tags = "2, 3, 4, 5, 6")]
+  pub value: Option<Value>,
+  #[prost(string, tag = "7")]
+  pub unit: String,
+  #[prost(string, tag = "8")]
+  pub unit_symbol: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct MeterReading {
+  #[prost(message, repeated, tag = "1")]
+  pub registers: Vec<Register>,
+}
+
+impl From<&DlmsObisCode> for ObisCode {
+  fn from(code: &DlmsObisCode) -> Self {
+    let [a, b, c, d, e, f] = ObisKey::from(code).groups().map(u32::from);
+    Self { a, b, c, d, e, f }
+  }
+}
+
+fn to_value(value: &Data) -> Option<Value> {
+  Some(match value {
+    Data::Null => return None,
+    Data::Integer(n) => Value::Integer((*n).into()),
+    Data::Long(n) => Value::Integer((*n).into()),
+    Data::DoubleLong(n) => Value::Integer((*n).into()),
+    Data::Long64(n) => Value::Integer(*n),
+    Data::Unsigned(n) => Value::Unsigned((*n).into()),
+    Data::LongUnsigned(n) => Value::Unsigned((*n).into()),
+    Data::DoubleLongUnsigned(n) => Value::Unsigned((*n).into()),
+    Data::Long64Unsigned(n) => Value::Unsigned(*n),
+    Data::Enum(n) => Value::Unsigned((*n).into()),
+    Data::Float32(n) => Value::Float((*n).into()),
+    Data::Float64(n) => Value::Float(*n),
+    Data::OctetString(bytes) => Value::Octets(bytes.clone()),
+    value => Value::Text(format_value(value).into_owned()),
+  })
+}
+
+/// Convert a reading into its Protocol Buffers message.
+pub fn to_proto(reading: &Reading) -> MeterReading {
+  let registers = reading.iter().map(|(code, reg)| Register {
+    code: Some(code.into()),
+    value: to_value(reg.value()),
+    unit: reg.unit().map(|unit| format!("{:?}", unit)).unwrap_or_default(),
+    unit_symbol: reg.unit().and_then(|unit| unit.as_str()).unwrap_or_default().to_owned(),
+  }).collect();
+
+  MeterReading { registers }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use prost::Message;
+
+  use crate::fixture::reading;
+
+  fn register(value: Option<Value>) -> Register {
+    let code = DlmsObisCode::new(1, 0, 1, 8, 0, 255);
+    Register { code: Some((&code).into()), value, unit: "WattHour".into(), unit_symbol: "Wh".into() }
+  }
+
+  #[test]
+  fn round_trip() {
+    let values = [
+      None,
+      Some(Value::Integer(-1)),
+      Some(Value::Integer(i64::MIN)),
+      Some(Value::Unsigned(u64::MAX)),
+      Some(Value::Float(-0.5)),
+      Some(Value::Text("2021-09-11 09:13:20".into())),
+      Some(Value::Octets(vec![0x00, 0xff])),
+    ];
+
+    for value in values {
+      let message = MeterReading { registers: vec![register(value)] };
+      assert_eq!(MeterReading::decode(message.encode_to_vec().as_slice()).unwrap(), message);
+    }
+  }
+
+  #[test]
+  fn wire_format() {
+    let register = Register { value: Some(Value::Integer(-1)), unit: String::new(), unit_symbol: String::new(), ..register(None) };
+
+    // The tags and types match `proto/smart_meter.proto`, e.g. `integer` is a zigzag-encoded `sint64`.
+    assert_eq!(register.encode_to_vec(), [0x0a, 0x09, 0x08, 0x01, 0x18, 0x01, 0x20, 0x08, 0x30, 0xff, 0x01, 0x10, 0x01]);
+  }
+
+  #[test]
+  fn from_reading() {
+    let reading = reading(&[
+      ([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x04, 0xd2], None),
+      ([1, 0, 1, 7, 0, 255], &[0x10, 0xff, 0xfe], None),
+      ([0, 0, 96, 1, 0, 255], &[0x09, 0x02, 0x01, 0x02], None),
+    ]);
+
+    let message = to_proto(&reading);
+    let values = message.registers.iter().map(|reg| reg.value.clone()).collect::<Vec<_>>();
+    assert_eq!(values, [Some(Value::Octets(vec![0x01, 0x02])), Some(Value::Integer(-2)), Some(Value::Unsigned(1234))]);
+    assert_eq!(MeterReading::decode(message.encode_to_vec().as_slice()).unwrap(), message);
+  }
+}
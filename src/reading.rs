@@ -0,0 +1,655 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+
+use dlms_cosem::{Data, DateTime, ObisCode, ObisMap, Unit};
+
+use crate::value::{as_f64, as_u64};
+
+const CLOCK: ObisCode = ObisCode::new(0, 0, 1, 0, 0, 255);
+const ACTIVE_TARIFF: ObisCode = ObisCode::new(0, 0, 96, 14, 0, 255);
+const ENERGY_IMPORT: ObisCode = ObisCode::new(1, 0, 1, 8, 0, 255);
+const ENERGY_EXPORT: ObisCode = ObisCode::new(1, 0, 2, 8, 0, 255);
+const POWER_FACTOR: ObisCode = ObisCode::new(1, 0, 13, 7, 0, 255);
+const FREQUENCY: ObisCode = ObisCode::new(1, 0, 14, 7, 0, 255);
+const FIRMWARE_VERSION: ObisCode = ObisCode::new(1, 0, 0, 2, 0, 255);
+const EQUIPMENT_ID: ObisCode = ObisCode::new(0, 0, 96, 1, 0, 255);
+const POWER_FAILURES: ObisCode = ObisCode::new(0, 0, 96, 7, 21, 255);
+const LONG_POWER_FAILURES: ObisCode = ObisCode::new(0, 0, 96, 7, 9, 255);
+const BREAKER_STATE: ObisCode = ObisCode::new(0, 0, 96, 3, 10, 255);
+
+/// Power failure counters reported by a meter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PowerFailureInfo {
+  /// Number of power failures in any phase (0-0:96.7.21).
+  pub count: Option<u64>,
+  /// Number of long power failures in any phase (0-0:96.7.9).
+  pub long_count: Option<u64>,
+}
+
+/// The control state of a meter's disconnect control (0-0:96.3.10).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BreakerState {
+  /// The supply is disconnected.
+  Disconnected,
+  /// The supply is connected.
+  Connected,
+  /// The supply is disconnected, but may be reconnected locally, e.g. with a button on the meter.
+  ReadyForReconnection,
+}
+
+/// The equipment identifier (0-0:96.1.0), usually the serial number of the meter.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct EquipmentId(String);
+
+impl EquipmentId {
+  /// The identifier as string.
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+}
+
+impl fmt::Display for EquipmentId {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+/// The clock status of a date-time, see [`Reading::clock_status`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockStatus(u8);
+
+impl ClockStatus {
+  /// The raw status byte.
+  pub const fn bits(&self) -> u8 {
+    self.0
+  }
+
+  /// Whether the time is invalid, e.g. after a power failure, and should not be trusted.
+  pub const fn clock_invalid(&self) -> bool {
+    self.0 & 0b0000_0001 != 0
+  }
+
+  /// Whether the time may be wrong.
+  pub const fn doubtful(&self) -> bool {
+    self.0 & 0b0000_0010 != 0
+  }
+
+  /// Whether the clock is based on a different time base.
+  pub const fn different_base(&self) -> bool {
+    self.0 & 0b0000_0100 != 0
+  }
+
+  /// Whether the status itself is invalid.
+  pub const fn status_invalid(&self) -> bool {
+    self.0 & 0b0000_1000 != 0
+  }
+
+  /// Whether daylight saving time is active.
+  pub const fn is_dst(&self) -> bool {
+    self.0 & 0b1000_0000 != 0
+  }
+}
+
+/// Instantaneous values of a single phase, in the units reported by the meter.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Phase {
+  /// Voltage (1-0:32.7.0, 52.7.0, 72.7.0).
+  pub voltage: Option<f64>,
+  /// Current (1-0:31.7.0, 51.7.0, 71.7.0).
+  pub current: Option<f64>,
+  /// Active power import (1-0:21.7.0, 41.7.0, 61.7.0).
+  pub active_power_import: Option<f64>,
+  /// Active power export (1-0:22.7.0, 42.7.0, 62.7.0).
+  pub active_power_export: Option<f64>,
+  /// Power factor (1-0:33.7.0, 53.7.0, 73.7.0), see [`Reading::power_factor`].
+  pub power_factor: Option<f64>,
+}
+
+/// A single reading, i.e. the registers decoded from one APDU.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reading {
+  obis_map: ObisMap,
+}
+
+impl Reading {
+  /// Get the underlying `ObisMap`.
+  pub fn into_inner(self) -> ObisMap {
+    self.obis_map
+  }
+
+  /// The meter clock (0-0:1.0.0), unless its status marks it as invalid.
+  pub fn timestamp(&self) -> Option<DateTime> {
+    timestamp(&self.obis_map)
+  }
+
+  /// The status of the meter clock (0-0:1.0.0), if reported by the meter.
+  ///
+  /// `DateTime` does not expose the status, so this is only available while the clock
+  /// is still an octet string, i.e. without [`convert::date_time`](crate::convert::date_time).
+  pub fn clock_status(&self) -> Option<ClockStatus> {
+    clock_status(&self.obis_map)
+  }
+
+  /// The currently active tariff, if reported by the meter.
+  pub fn tariff(&self) -> Option<u8> {
+    let reg = self.obis_map.get(&ACTIVE_TARIFF)?;
+    as_u64(reg.value())?.try_into().ok()
+  }
+
+  /// The active firmware version (1-0:0.2.0), if reported by the meter.
+  ///
+  /// Octet strings are decoded as UTF-8, so this also works without [`convert::utf8_string`](crate::convert::utf8_string).
+  pub fn firmware_version(&self) -> Option<String> {
+    match self.obis_map.get(&FIRMWARE_VERSION)?.value() {
+      Data::OctetString(s) => String::from_utf8(s.clone()).ok(),
+      Data::Utf8String(s) => Some(s.clone()),
+      _ => None,
+    }
+  }
+
+  /// The equipment identifier (0-0:96.1.0), if reported by the meter.
+  pub fn equipment_id(&self) -> Option<EquipmentId> {
+    equipment_id(&self.obis_map)
+  }
+
+  /// The disconnect control state (0-0:96.3.10), if reported by the meter.
+  pub fn breaker_state(&self) -> Option<BreakerState> {
+    match as_u64(self.obis_map.get(&BREAKER_STATE)?.value())? {
+      0 => Some(BreakerState::Disconnected),
+      1 => Some(BreakerState::Connected),
+      2 => Some(BreakerState::ReadyForReconnection),
+      _ => None,
+    }
+  }
+
+  /// The power failure counters, if reported by the meter.
+  ///
+  /// Durations are only available from the power failure event log (1-0:99.97.0),
+  /// which is an array and therefore not decoded into the register map.
+  pub fn power_failures(&self) -> Option<PowerFailureInfo> {
+    let count = self.obis_map.get(&POWER_FAILURES).and_then(|reg| as_u64(reg.value()));
+    let long_count = self.obis_map.get(&LONG_POWER_FAILURES).and_then(|reg| as_u64(reg.value()));
+
+    if count.is_none() && long_count.is_none() {
+      return None
+    }
+
+    Some(PowerFailureInfo { count, long_count })
+  }
+
+  /// Total active energy import (1-0:1.8.0) in kWh.
+  ///
+  /// Returns `None` if the register is absent or not reported in Wh, the only energy unit defined by DLMS.
+  /// The scaler is applied while decoding, see [`apply_scalers`](crate::apply_scalers) for signed values.
+  pub fn energy_import_kwh(&self) -> Option<f64> {
+    self.kwh(&ENERGY_IMPORT)
+  }
+
+  /// Total active energy export (1-0:2.8.0) in kWh, see [`energy_import_kwh`](Self::energy_import_kwh).
+  pub fn energy_export_kwh(&self) -> Option<f64> {
+    self.kwh(&ENERGY_EXPORT)
+  }
+
+  /// Instantaneous supply frequency (1-0:14.7.0) in Hz.
+  ///
+  /// Returns `None` if the register is absent or not reported in Hz.
+  pub fn frequency_hz(&self) -> Option<f64> {
+    let reg = self.obis_map.get(&FREQUENCY)?;
+
+    match reg.unit() {
+      Some(Unit::Hertz) => as_f64(reg.value()),
+      _ => None,
+    }
+  }
+
+  /// Instantaneous power factor (1-0:13.7.0).
+  ///
+  /// Power factors are usually signed, which `dlms_cosem` leaves unscaled, so signed integers
+  /// return `None` until they are scaled with [`apply_scalers`](crate::apply_scalers).
+  pub fn power_factor(&self) -> Option<f64> {
+    power_factor(self.obis_map.get(&POWER_FACTOR)?.value())
+  }
+
+  fn kwh(&self, code: &ObisCode) -> Option<f64> {
+    let reg = self.obis_map.get(code)?;
+
+    match reg.unit() {
+      Some(Unit::WattHour) => Some(as_f64(reg.value())? / 1000.0),
+      _ => None,
+    }
+  }
+
+  /// Active energy import (1-0:1.8.x) per tariff, in the unit reported by the meter.
+  ///
+  /// Single-tariff meters only reporting the total (1-0:1.8.0) return an empty map.
+  pub fn tariff_energy_import(&self) -> BTreeMap<u8, f64> {
+    self.tariff_registers(1, 8)
+  }
+
+  /// Active energy export (1-0:2.8.x) per tariff, in the unit reported by the meter.
+  ///
+  /// Single-tariff meters only reporting the total (1-0:2.8.0) return an empty map.
+  pub fn tariff_energy_export(&self) -> BTreeMap<u8, f64> {
+    self.tariff_registers(2, 8)
+  }
+
+  /// The instantaneous values of phases L1, L2 and L3.
+  ///
+  /// Values not reported by the meter, e.g. L2 and L3 on single-phase meters, are `None`.
+  pub fn phases(&self) -> [Phase; 3] {
+    let value = |c: u8| {
+      let reg = self.obis_map.get(&ObisCode::new(1, 0, c, 7, 0, 255))?;
+      as_f64(reg.value())
+    };
+
+    [20, 40, 60].map(|offset| Phase {
+      voltage: value(offset + 12),
+      current: value(offset + 11),
+      active_power_import: value(offset + 1),
+      active_power_export: value(offset + 2),
+      power_factor: self.obis_map.get(&ObisCode::new(1, 0, offset + 13, 7, 0, 255)).and_then(|reg| power_factor(reg.value())),
+    })
+  }
+
+  /// Total harmonic distortion of the voltage (1-0:32.7.124, 52.7.124, 72.7.124) of phases L1, L2 and L3 in %.
+  ///
+  /// Values not reported by the meter or reported in a unit other than % are `None`.
+  pub fn voltage_thd_percent(&self) -> [Option<f64>; 3] {
+    [32, 52, 72].map(|c| self.thd_percent(c))
+  }
+
+  /// Total harmonic distortion of the current (1-0:31.7.124, 51.7.124, 71.7.124) of phases L1, L2 and L3 in %,
+  /// see [`voltage_thd_percent`](Self::voltage_thd_percent).
+  pub fn current_thd_percent(&self) -> [Option<f64>; 3] {
+    [31, 51, 71].map(|c| self.thd_percent(c))
+  }
+
+  fn thd_percent(&self, c: u8) -> Option<f64> {
+    let reg = self.obis_map.get(&ObisCode::new(1, 0, c, 7, 124, 255))?;
+
+    match reg.unit() {
+      None | Some(Unit::Percent) => as_f64(reg.value()),
+      _ => None,
+    }
+  }
+
+  fn tariff_registers(&self, c: u8, d: u8) -> BTreeMap<u8, f64> {
+    (1..=63).filter_map(|tariff| {
+      let reg = self.obis_map.get(&ObisCode::new(1, 0, c, d, tariff, 255))?;
+      Some((tariff, as_f64(reg.value())?))
+    }).collect()
+  }
+}
+
+/// Error returned by [`ObisMapExt::try_convert`] if the code is not present.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingCode(pub ObisCode);
+
+impl fmt::Display for MissingCode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "missing code {}", self.0)
+  }
+}
+
+impl std::error::Error for MissingCode {}
+
+/// Error returned by [`ObisMapExt::remap_codes`] if multiple registers would end up with the same code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemapCollision(pub ObisCode);
+
+impl fmt::Display for RemapCollision {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "multiple registers remapped to {}", self.0)
+  }
+}
+
+impl std::error::Error for RemapCollision {}
+
+/// Extensions for `ObisMap`, also usable on [`Reading`].
+pub trait ObisMapExt {
+  /// Like `ObisMap::convert`, but returns an error if `code` is not present.
+  fn try_convert(&mut self, code: &ObisCode, f: impl FnMut(Data) -> Data) -> Result<(), MissingCode>;
+
+  /// Rename registers according to `table`, mapping nonstandard codes to standard ones.
+  ///
+  /// Codes not in `table` are kept. If two registers would end up with the same code,
+  /// an error is returned and the map is left unchanged.
+  ///
+  /// Only the keys are changed, `Register::obis_code` still returns the original code since
+  /// `dlms_cosem` provides no way to construct a `Register`. Use the keys of the map instead.
+  fn remap_codes(&mut self, table: &BTreeMap<ObisCode, ObisCode>) -> Result<(), RemapCollision>;
+}
+
+impl ObisMapExt for ObisMap {
+  fn try_convert(&mut self, code: &ObisCode, f: impl FnMut(Data) -> Data) -> Result<(), MissingCode> {
+    if !self.contains_key(code) {
+      return Err(MissingCode(code.clone()))
+    }
+
+    self.convert(code, f);
+    Ok(())
+  }
+
+  fn remap_codes(&mut self, table: &BTreeMap<ObisCode, ObisCode>) -> Result<(), RemapCollision> {
+    let target = |code: &ObisCode| table.get(code).unwrap_or(code).clone();
+
+    let mut targets = Vec::with_capacity(self.len());
+    for code in self.keys() {
+      let target = target(code);
+      if targets.contains(&target) {
+        return Err(RemapCollision(target))
+      }
+      targets.push(target);
+    }
+
+    let registers = std::mem::take(&mut **self);
+    **self = registers.into_iter().map(|(code, reg)| (target(&code), reg)).collect();
+    Ok(())
+  }
+}
+
+/// Get a power factor, unless it is a raw signed integer whose scaler was not applied.
+fn power_factor(value: &Data) -> Option<f64> {
+  match *value {
+    Data::Float32(n) => Some(n.into()),
+    Data::Float64(n) => Some(n),
+    // Scaled by `dlms_cosem` if there is a scaler, exact otherwise.
+    Data::LongUnsigned(n) => Some(n.into()),
+    Data::DoubleLongUnsigned(n) => Some(n.into()),
+    _ => None,
+  }
+}
+
+/// Get the equipment identifier (0-0:96.1.0) from a reading, if present.
+pub(crate) fn equipment_id(obis_map: &ObisMap) -> Option<EquipmentId> {
+  let id = match obis_map.get(&EQUIPMENT_ID)?.value() {
+    Data::OctetString(s) => String::from_utf8(s.clone()).ok()?,
+    Data::Utf8String(s) => s.clone(),
+    _ => return None,
+  };
+
+  Some(EquipmentId(id))
+}
+
+fn clock_status(obis_map: &ObisMap) -> Option<ClockStatus> {
+  match obis_map.get(&CLOCK)?.value() {
+    Data::OctetString(bytes) if bytes.len() == 12 && bytes[11] != 0xff => Some(ClockStatus(bytes[11])),
+    _ => None,
+  }
+}
+
+/// Get the meter clock (0-0:1.0.0) from a reading, if present and not marked as invalid.
+pub(crate) fn timestamp(obis_map: &ObisMap) -> Option<DateTime> {
+  if clock_status(obis_map).is_some_and(|status| status.clock_invalid()) {
+    return None
+  }
+
+  match obis_map.get(&CLOCK)?.value() {
+    Data::DateTime(date_time) => Some(date_time.clone()),
+    Data::OctetString(bytes) => DateTime::parse(bytes).ok().map(|(_, date_time)| date_time),
+    _ => None,
+  }
+}
+
+/// Convert a date-time to seconds since 1970-01-01 in the meter's local time.
+///
+/// Returns `None` if the date is not specified, i.e. contains wildcards like a year of 0xffff
+/// or a month of 0xff, or special values like the last day of the month.
+/// Unspecified time fields count as 0.
+pub(crate) fn local_seconds(date_time: &DateTime) -> Option<i64> {
+  // The fields of `DateTime` are private, so its `Display` output is the only way to get them.
+  // Dates are formatted as is, e.g. as `65535-255-255`, while unspecified time fields are formatted as 0.
+  let s = date_time.to_string();
+  let (date, time) = s.split_once('T')?;
+
+  let mut date = date.split('-').map(|field| field.parse::<i64>().ok());
+  let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+  if year == 0xffff || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+    return None
+  }
+
+  let mut time = time.get(..8)?.split(':').map(|field| field.parse::<i64>().ok());
+  let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+
+  // Days from civil, see http://howardhinnant.github.io/date_algorithms.html.
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  let days = era * 146097 + day_of_era - 719468;
+
+  Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+impl From<ObisMap> for Reading {
+  fn from(obis_map: ObisMap) -> Self {
+    Self { obis_map }
+  }
+}
+
+impl Deref for Reading {
+  type Target = ObisMap;
+
+  fn deref(&self) -> &Self::Target {
+    &self.obis_map
+  }
+}
+
+impl DerefMut for Reading {
+  fn deref_mut(&mut self) -> &mut Self::Target {
+    &mut self.obis_map
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::apply_scalers;
+  use crate::fixture::{notification, obis_map, reading};
+
+  const WATT_HOUR: u8 = 30;
+  const WATT: u8 = 27;
+  const AMPERE: u8 = 33;
+  const VOLT: u8 = 35;
+  const HERTZ: u8 = 44;
+  const PERCENT: u8 = 56;
+  const COUNT: u8 = 255;
+
+  #[test]
+  fn frequency() {
+    for (scaler, raw) in [(-2, [0x13, 0x88]), (-1, [0x01, 0xf4]), (0, [0x00, 0x32])] {
+      let reading = reading(&[([1, 0, 14, 7, 0, 255], &[0x12, raw[0], raw[1]], Some((scaler, HERTZ)))]);
+      assert_eq!(reading.frequency_hz(), Some(50.0), "scaler {}", scaler);
+    }
+
+    assert_eq!(reading(&[([1, 0, 14, 7, 0, 255], &[0x12, 0x00, 0x32], Some((0, 33)))]).frequency_hz(), None);
+    assert_eq!(reading(&[]).frequency_hz(), None);
+  }
+
+  #[test]
+  fn power_factor() {
+    assert_eq!(reading(&[]).power_factor(), None);
+
+    let apdu = notification(&[
+      ([1, 0, 13, 7, 0, 255], &[0x10, 0x03, 0xe6], Some((-3, COUNT))),
+      ([1, 0, 33, 7, 0, 255], &[0x12, 0x03, 0xe6], Some((-3, COUNT))),
+    ]);
+    let mut registers = obis_map(&apdu);
+
+    let reading = Reading::from(registers.clone());
+    assert_eq!(reading.power_factor(), None);
+    assert_eq!(reading.phases()[0].power_factor, Some(0.998f32.into()));
+
+    apply_scalers(&mut registers, &apdu).unwrap();
+    let reading = Reading::from(registers);
+    assert_eq!(reading.power_factor(), Some(0.998f32.into()));
+  }
+
+  #[test]
+  fn remap_codes() {
+    let nonstandard = ObisCode::new(1, 1, 1, 8, 0, 255);
+    let mut reading = reading(&[
+      ([1, 1, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x30, 0x39], Some((0, 30))),
+      ([1, 0, 2, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x01], Some((0, 30))),
+    ]);
+
+    let table = BTreeMap::from([(nonstandard.clone(), ENERGY_IMPORT)]);
+    reading.remap_codes(&table).unwrap();
+    assert_eq!(reading.energy_import_kwh(), Some(12.345));
+    assert_eq!(reading[&ENERGY_IMPORT].obis_code(), &nonstandard);
+
+    let table = BTreeMap::from([(ENERGY_EXPORT, ENERGY_IMPORT)]);
+    let expected = reading.clone();
+    assert_eq!(reading.remap_codes(&table), Err(RemapCollision(ENERGY_IMPORT)));
+    assert_eq!(reading, expected);
+  }
+
+  fn date_time(date: [u8; 4]) -> DateTime {
+    let bytes = [&date[..], &[0x06, 0x09, 0x0d, 0x14, 0x00, 0xff, 0x88, 0x80]].concat();
+    DateTime::parse(&bytes).unwrap().1
+  }
+
+  #[test]
+  fn local_seconds() {
+    assert_eq!(super::local_seconds(&date_time([0x07, 0xe5, 0x09, 0x0b])), Some(1631351600));
+    assert_eq!(super::local_seconds(&date_time([0x07, 0xb2, 0x01, 0x01])), Some(9 * 3600 + 13 * 60 + 20));
+  }
+
+  #[test]
+  fn local_seconds_wildcards() {
+    // Unspecified year, month and day, end and start of daylight saving time and last and second last day of the month.
+    for date in [[0xff, 0xff, 0x09, 0x0b], [0x07, 0xe5, 0xff, 0x0b], [0x07, 0xe5, 0x09, 0xff], [0x07, 0xe5, 0xfd, 0x0b], [0x07, 0xe5, 0xfe, 0x0b], [0x07, 0xe5, 0x09, 0xfe], [0x07, 0xe5, 0x09, 0xfd]] {
+      assert_eq!(super::local_seconds(&date_time(date)), None, "{:02x?}", date);
+    }
+  }
+
+  #[test]
+  fn clock_status() {
+    let clock = |status: u8| {
+      let value = [&[0x09, 0x0c, 0x07, 0xe5, 0x09, 0x0b, 0x06, 0x09, 0x0d, 0x14, 0x00, 0xff, 0x88][..], &[status]].concat();
+      reading(&[([0, 0, 1, 0, 0, 255], &value, None)])
+    };
+
+    let reading = clock(0x80);
+    assert!(reading.clock_status().unwrap().is_dst());
+    assert_eq!(reading.timestamp().map(|t| t.to_string()), Some("2021-09-11T09:13:20.00+02:00".into()));
+
+    let reading = clock(0x01);
+    assert!(reading.clock_status().unwrap().clock_invalid());
+    assert_eq!(reading.timestamp(), None);
+
+    assert_eq!(clock(0xff).clock_status(), None);
+    assert!(clock(0xff).timestamp().is_some());
+  }
+
+  #[test]
+  fn tariff() {
+    let multi_tariff = reading(&[
+      ([0, 0, 96, 14, 0, 255], &[0x12, 0x00, 0x02], None),
+      ([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x30, 0xd4], Some((0, WATT_HOUR))),
+      ([1, 0, 1, 8, 1, 255], &[0x06, 0x00, 0x00, 0x30, 0x39], Some((0, WATT_HOUR))),
+      ([1, 0, 1, 8, 2, 255], &[0x06, 0x00, 0x00, 0x00, 0x9b], Some((0, WATT_HOUR))),
+      ([1, 0, 2, 8, 1, 255], &[0x06, 0x00, 0x00, 0x00, 0x01], Some((0, WATT_HOUR))),
+    ]);
+
+    assert_eq!(multi_tariff.tariff(), Some(2));
+    assert_eq!(multi_tariff.tariff_energy_import(), BTreeMap::from([(1, 12345.0), (2, 155.0)]));
+    assert_eq!(multi_tariff.tariff_energy_export(), BTreeMap::from([(1, 1.0)]));
+
+    assert_eq!(reading(&[([0, 0, 96, 14, 0, 255], b"\x09\x040001", None)]).tariff(), Some(1));
+
+    let single_tariff = reading(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x30, 0xd4], Some((0, WATT_HOUR)))]);
+    assert_eq!(single_tariff.tariff(), None);
+    assert!(single_tariff.tariff_energy_import().is_empty());
+  }
+
+  #[test]
+  fn power_failures() {
+    let both = reading(&[
+      ([0, 0, 96, 7, 21, 255], &[0x12, 0x00, 0x05], None),
+      ([0, 0, 96, 7, 9, 255], &[0x12, 0x00, 0x01], None),
+    ]);
+    assert_eq!(both.power_failures(), Some(PowerFailureInfo { count: Some(5), long_count: Some(1) }));
+
+    let long_only = reading(&[([0, 0, 96, 7, 9, 255], &[0x06, 0x00, 0x00, 0x00, 0x02], None)]);
+    assert_eq!(long_only.power_failures(), Some(PowerFailureInfo { count: None, long_count: Some(2) }));
+
+    assert_eq!(reading(&[]).power_failures(), None);
+  }
+
+  #[test]
+  fn phases_single_phase() {
+    let reading = reading(&[
+      ([1, 0, 32, 7, 0, 255], &[0x12, 0x08, 0xfc], Some((-1, VOLT))),
+      ([1, 0, 31, 7, 0, 255], &[0x12, 0x01, 0xf4], Some((-2, AMPERE))),
+      ([1, 0, 21, 7, 0, 255], &[0x06, 0x00, 0x00, 0x04, 0x7e], Some((0, WATT))),
+      ([1, 0, 22, 7, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x00], Some((0, WATT))),
+    ]);
+
+    let [l1, l2, l3] = reading.phases();
+    assert_eq!(l1, Phase {
+      voltage: Some(230.0),
+      current: Some(5.0),
+      active_power_import: Some(1150.0),
+      active_power_export: Some(0.0),
+      power_factor: None,
+    });
+    assert_eq!(l2, Phase::default());
+    assert_eq!(l3, Phase::default());
+  }
+
+  #[test]
+  fn energy_kwh() {
+    for (scaler, raw, kwh) in [(0, 12345, 12.345), (1, 12345, 123.45), (3, 12, 12.0)] {
+      let raw = u32::to_be_bytes(raw);
+      let reading = reading(&[
+        ([1, 0, 1, 8, 0, 255], &[0x06, raw[0], raw[1], raw[2], raw[3]], Some((scaler, WATT_HOUR))),
+        ([1, 0, 2, 8, 0, 255], &[0x06, raw[0], raw[1], raw[2], raw[3]], Some((scaler, WATT_HOUR))),
+      ]);
+      assert_eq!(reading.energy_import_kwh(), Some(kwh), "scaler {}", scaler);
+      assert_eq!(reading.energy_export_kwh(), Some(kwh), "scaler {}", scaler);
+    }
+
+    assert_eq!(reading(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x30, 0x39], Some((0, WATT)))]).energy_import_kwh(), None);
+    assert_eq!(reading(&[]).energy_import_kwh(), None);
+  }
+
+  #[test]
+  fn try_convert() {
+    let mut reading = reading(&[([0, 0, 96, 1, 0, 255], b"\x09\x0412ab", None)]);
+
+    assert_eq!(reading.try_convert(&EQUIPMENT_ID, crate::convert::utf8_string), Ok(()));
+    assert_eq!(reading[&EQUIPMENT_ID].value(), &Data::Utf8String("12ab".into()));
+
+    let expected = reading.clone();
+    assert_eq!(reading.try_convert(&CLOCK, crate::convert::date_time), Err(MissingCode(CLOCK)));
+    assert_eq!(reading, expected);
+  }
+
+  #[test]
+  fn breaker_state() {
+    let breaker_state = |state: u8| reading(&[([0, 0, 96, 3, 10, 255], &[0x16, state], None)]).breaker_state();
+
+    assert_eq!(breaker_state(0), Some(BreakerState::Disconnected));
+    assert_eq!(breaker_state(1), Some(BreakerState::Connected));
+    assert_eq!(breaker_state(2), Some(BreakerState::ReadyForReconnection));
+    assert_eq!(breaker_state(3), None);
+    assert_eq!(reading(&[]).breaker_state(), None);
+  }
+
+  #[test]
+  fn thd() {
+    let reading = reading(&[
+      ([1, 0, 32, 7, 124, 255], &[0x12, 0x00, 0x1e], Some((-1, PERCENT))),
+      ([1, 0, 52, 7, 124, 255], &[0x12, 0x00, 0x1e], Some((-1, VOLT))),
+      ([1, 0, 31, 7, 124, 255], &[0x12, 0x00, 0x0c], None),
+      ([1, 0, 71, 7, 124, 255], &[0x12, 0x00, 0x0c], Some((0, PERCENT))),
+    ]);
+
+    assert_eq!(reading.voltage_thd_percent(), [Some(3.0), None, None]);
+    assert_eq!(reading.current_thd_percent(), [Some(12.0), None, Some(12.0)]);
+  }
+}
@@ -0,0 +1,77 @@
+use std::borrow::Cow;
+
+use serialport::{DataBits, Parity, SerialPortBuilder, StopBits};
+
+/// Serial line settings for a meter's interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialConfig {
+  baud_rate: u32,
+  data_bits: DataBits,
+  parity: Parity,
+  stop_bits: StopBits,
+}
+
+impl SerialConfig {
+  /// Create a new configuration.
+  ///
+  /// # Panics
+  ///
+  /// Panics if the baud rate is zero or if five data bits are combined with two stop bits,
+  /// which most UARTs interpret as 1.5 stop bits. When used in a `const`,
+  /// invalid combinations are rejected at build time.
+  pub const fn new(baud_rate: u32, data_bits: DataBits, parity: Parity, stop_bits: StopBits) -> Self {
+    assert!(baud_rate > 0, "baud rate must not be zero");
+    assert!(
+      !matches!((data_bits, stop_bits), (DataBits::Five, StopBits::Two)),
+      "five data bits cannot be used with two stop bits",
+    );
+
+    Self { baud_rate, data_bits, parity, stop_bits }
+  }
+
+  /// 8 data bits, even parity, 1 stop bit, as used by M-Bus meters.
+  pub const fn new_8e1(baud_rate: u32) -> Self {
+    Self::new(baud_rate, DataBits::Eight, Parity::Even, StopBits::One)
+  }
+
+  /// 8 data bits, no parity, 1 stop bit.
+  pub const fn new_8n1(baud_rate: u32) -> Self {
+    Self::new(baud_rate, DataBits::Eight, Parity::None, StopBits::One)
+  }
+
+  /// 7 data bits, even parity, 1 stop bit, as used by IEC 62056-21 meters.
+  pub const fn new_7e1(baud_rate: u32) -> Self {
+    Self::new(baud_rate, DataBits::Seven, Parity::Even, StopBits::One)
+  }
+
+  pub const fn baud_rate(&self) -> u32 {
+    self.baud_rate
+  }
+
+  pub const fn data_bits(&self) -> DataBits {
+    self.data_bits
+  }
+
+  pub const fn parity(&self) -> Parity {
+    self.parity
+  }
+
+  pub const fn stop_bits(&self) -> StopBits {
+    self.stop_bits
+  }
+
+  /// Create a [`SerialPortBuilder`] for the given path using this configuration.
+  pub fn builder<'a>(&self, path: impl Into<Cow<'a, str>>) -> SerialPortBuilder {
+    serialport::new(path, self.baud_rate)
+      .data_bits(self.data_bits)
+      .parity(self.parity)
+      .stop_bits(self.stop_bits)
+  }
+}
+
+impl Default for SerialConfig {
+  /// 2400 baud, 8E1.
+  fn default() -> Self {
+    Self::new_8e1(2400)
+  }
+}
@@ -0,0 +1,102 @@
+use std::error::Error as StdError;
+use std::fmt;
+
+use dlms_cosem::ObisMap;
+
+use crate::LatestCache;
+
+/// Error returned by a [`Sink`].
+pub type SinkError = Box<dyn StdError + Send + Sync>;
+
+/// A consumer of readings, e.g. a log, database or exporter.
+pub trait Sink {
+  /// Consume a single reading, returning an error if it could not be processed.
+  ///
+  /// An error does not end the sink, so it is called again with the next reading.
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError>;
+}
+
+impl<F: FnMut(&ObisMap) -> Result<(), SinkError>> Sink for F {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    self(reading)
+  }
+}
+
+impl Sink for LatestCache {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    self.update(reading);
+    Ok(())
+  }
+}
+
+/// Dispatches every reading to multiple sinks.
+///
+/// All sinks are called even if some of them fail, the first error is returned afterwards.
+#[derive(Default)]
+pub struct FanOut(pub Vec<Box<dyn Sink + Send>>);
+
+impl fmt::Debug for FanOut {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_tuple("FanOut").field(&self.0.len()).finish()
+  }
+}
+
+impl FanOut {
+  /// Add a sink.
+  pub fn with_sink(mut self, sink: impl Sink + Send + 'static) -> Self {
+    self.0.push(Box::new(sink));
+    self
+  }
+}
+
+impl Sink for FanOut {
+  fn consume(&mut self, reading: &ObisMap) -> Result<(), SinkError> {
+    let mut result = Ok(());
+
+    for sink in self.0.iter_mut() {
+      if let Err(err) = sink.consume(reading) {
+        if result.is_ok() {
+          result = Err(err);
+        }
+      }
+    }
+
+    result
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::sync::{Arc, Mutex};
+
+  use crate::fixture::reading;
+
+  #[test]
+  fn fan_out() {
+    let calls = Arc::new(Mutex::new(Vec::new()));
+
+    let recorder = |name: &'static str| {
+      let calls = Arc::clone(&calls);
+      move |_: &ObisMap| -> Result<(), SinkError> {
+        calls.lock().unwrap().push(name);
+        Ok(())
+      }
+    };
+
+    let mut fan_out = FanOut::default()
+      .with_sink(recorder("first"))
+      .with_sink(|_: &ObisMap| -> Result<(), SinkError> { Err("first error".into()) })
+      .with_sink(|_: &ObisMap| -> Result<(), SinkError> { Err("second error".into()) })
+      .with_sink(recorder("last"));
+
+    let reading = reading(&[([1, 0, 1, 8, 0, 255], &[0x06, 0x00, 0x00, 0x00, 0x01], None)]);
+
+    assert_eq!(fan_out.consume(&reading).unwrap_err().to_string(), "first error");
+    assert_eq!(*calls.lock().unwrap(), ["first", "last"]);
+
+    assert!(fan_out.consume(&reading).is_err());
+    assert_eq!(*calls.lock().unwrap(), ["first", "last", "first", "last"]);
+  }
+}
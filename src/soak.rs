@@ -0,0 +1,85 @@
+use std::io::{self, Read};
+use std::time::Duration;
+
+use dlms_cosem::Dlms;
+
+use crate::{Error, SmartMeter, Stats};
+
+/// The result of [`SmartMeter::soak`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct SoakReport {
+  /// Number of readings containing registers.
+  pub readings: u64,
+  /// Number of [`Error::DecryptionFailed`] errors, usually caused by a wrong key.
+  pub decryption_failures: u64,
+  /// Number of [`Error::InvalidFormat`] errors.
+  pub invalid_format: u64,
+  /// Number of [`Error::LikelyBaudMismatch`] errors.
+  pub baud_mismatches: u64,
+  /// Number of I/O errors other than timeouts.
+  pub io_errors: u64,
+  /// Number of all other errors.
+  pub other_errors: u64,
+  /// Number of bytes which were not part of a decoded reading.
+  pub skipped_bytes: u64,
+  /// The interval between readings, see [`SmartMeter::observed_interval`].
+  pub interval: Option<Duration>,
+  /// The decoding statistics.
+  pub stats: Stats,
+}
+
+impl SoakReport {
+  /// Whether readings were received without any errors.
+  pub fn is_healthy(&self) -> bool {
+    self.readings > 0
+      && self.decryption_failures == 0
+      && self.invalid_format == 0
+      && self.baud_mismatches == 0
+      && self.io_errors == 0
+      && self.other_errors == 0
+  }
+}
+
+impl<R: Read> SmartMeter<R> {
+  /// Read from `reader` for the given duration and report what was received, e.g. when commissioning a meter.
+  pub fn soak(reader: R, dlms: Dlms, duration: Duration) -> SoakReport {
+    Self::new(reader, dlms).soak_for(duration)
+  }
+
+  /// Like [`soak`](Self::soak), but using this instance, e.g. one with a transport header or a clock set.
+  pub fn soak_for(mut self, duration: Duration) -> SoakReport {
+    let deadline = self.now() + duration;
+
+    let mut report = SoakReport {
+      readings: 0,
+      decryption_failures: 0,
+      invalid_format: 0,
+      baud_mismatches: 0,
+      io_errors: 0,
+      other_errors: 0,
+      skipped_bytes: 0,
+      interval: None,
+      stats: Stats::default(),
+    };
+
+    while self.now() < deadline {
+      match self.read_obis_map(Some(deadline)) {
+        Ok(obis_map) => if !obis_map.is_empty() {
+          report.readings += 1;
+        },
+        Err(Error::DecryptionFailed(_)) => report.decryption_failures += 1,
+        Err(Error::InvalidFormat) => report.invalid_format += 1,
+        Err(Error::LikelyBaudMismatch) => report.baud_mismatches += 1,
+        Err(Error::Io(err)) if matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock) => (),
+        Err(Error::Io(_)) => report.io_errors += 1,
+        Err(_) => report.other_errors += 1,
+      }
+    }
+
+    report.stats = self.stats().clone();
+    report.skipped_bytes = report.stats.bytes_read().saturating_sub(report.stats.useful_bytes());
+    report.interval = self.observed_interval();
+    report
+  }
+}
@@ -0,0 +1,193 @@
+use std::io::Read;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use dlms_cosem::ObisMap;
+
+use crate::{Error, SmartMeter};
+
+/// Backpressure statistics of a [`ReaderHandle`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Backpressure {
+  sent: u64,
+  dropped: u64,
+  blocked: Duration,
+}
+
+impl Backpressure {
+  /// Number of items sent to the channel.
+  pub fn sent(&self) -> u64 {
+    self.sent
+  }
+
+  /// Number of items dropped because the channel was full, see [`SmartMeter::spawn_lossy`].
+  pub fn dropped(&self) -> u64 {
+    self.dropped
+  }
+
+  /// Total time the reader thread was blocked because the channel was full, see [`SmartMeter::spawn`].
+  pub fn blocked(&self) -> Duration {
+    self.blocked
+  }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+  sent: AtomicU64,
+  dropped: AtomicU64,
+  blocked_nanos: AtomicU64,
+}
+
+/// Iterator over the items of a reader thread, returned by [`SmartMeter::spawn`] and [`SmartMeter::spawn_lossy`].
+///
+/// The thread exits once the reader is exhausted, i.e. after sending [`Error::NoProgress`],
+/// or once this is dropped and it tries to send its next item.
+#[derive(Debug)]
+pub struct ReaderHandle {
+  rx: Receiver<Result<ObisMap, Error>>,
+  counters: Arc<Counters>,
+  thread: JoinHandle<()>,
+}
+
+impl ReaderHandle {
+  /// Get a snapshot of the backpressure statistics.
+  ///
+  /// Growing [`blocked`](Backpressure::blocked) time or [`dropped`](Backpressure::dropped) count
+  /// indicate that the consumer cannot keep up with the meter.
+  pub fn backpressure(&self) -> Backpressure {
+    Backpressure {
+      sent: self.counters.sent.load(Ordering::Relaxed),
+      dropped: self.counters.dropped.load(Ordering::Relaxed),
+      blocked: Duration::from_nanos(self.counters.blocked_nanos.load(Ordering::Relaxed)),
+    }
+  }
+
+  /// Whether the reader thread has exited.
+  pub fn is_finished(&self) -> bool {
+    self.thread.is_finished()
+  }
+
+  /// Stop the reader thread and wait for it to exit, discarding any items not received yet.
+  ///
+  /// A thread blocked in a read only exits once the read returns, so the reader should have a read timeout.
+  /// Returns an error if the thread panicked.
+  pub fn join(self) -> thread::Result<()> {
+    let Self { rx, thread, .. } = self;
+    drop(rx);
+    thread.join()
+  }
+}
+
+impl Iterator for ReaderHandle {
+  type Item = Result<ObisMap, Error>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.rx.recv().ok()
+  }
+}
+
+fn send(tx: &SyncSender<Result<ObisMap, Error>>, counters: &Counters, item: Result<ObisMap, Error>, lossy: bool) -> bool {
+  let item = match tx.try_send(item) {
+    Ok(()) => {
+      counters.sent.fetch_add(1, Ordering::Relaxed);
+      return true
+    },
+    Err(TrySendError::Full(_)) if lossy => {
+      counters.dropped.fetch_add(1, Ordering::Relaxed);
+      return true
+    },
+    Err(TrySendError::Full(item)) => item,
+    Err(TrySendError::Disconnected(_)) => return false,
+  };
+
+  let start = Instant::now();
+  let result = tx.send(item);
+  let blocked = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+  counters.blocked_nanos.fetch_add(blocked, Ordering::Relaxed);
+
+  if result.is_err() {
+    return false
+  }
+
+  counters.sent.fetch_add(1, Ordering::Relaxed);
+  true
+}
+
+fn spawn<R: Read + Send + 'static>(smart_meter: SmartMeter<R>, capacity: usize, lossy: bool) -> ReaderHandle {
+  let (tx, rx) = mpsc::sync_channel(capacity);
+  let counters = Arc::new(Counters::default());
+
+  let thread = {
+    let counters = Arc::clone(&counters);
+
+    thread::spawn(move || {
+      for item in smart_meter {
+        if !send(&tx, &counters, item, lossy) {
+          break
+        }
+      }
+    })
+  };
+
+  ReaderHandle { rx, counters, thread }
+}
+
+impl<R: Read + Send + 'static> SmartMeter<R> {
+  /// Read on a background thread, sending items through a channel holding up to `capacity` items.
+  ///
+  /// The thread blocks while the channel is full, see [`Backpressure::blocked`].
+  pub fn spawn(self, capacity: usize) -> ReaderHandle {
+    spawn(self, capacity, false)
+  }
+
+  /// Like [`spawn`](Self::spawn), but drops items while the channel is full instead of blocking,
+  /// see [`Backpressure::dropped`].
+  pub fn spawn_lossy(self, capacity: usize) -> ReaderHandle {
+    spawn(self, capacity, true)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::io::{self, Cursor};
+
+  use dlms_cosem::Dlms;
+
+  use crate::fixture::{FRAMES, KEY};
+
+  #[test]
+  fn ends_when_exhausted() {
+    let mut handle = SmartMeter::new(Cursor::new(FRAMES), Dlms::new(KEY)).spawn(1);
+
+    assert!(matches!(handle.next(), Some(Ok(_))));
+    assert!(matches!(handle.next(), Some(Err(Error::NoProgress))));
+    assert!(handle.next().is_none());
+    assert_eq!(handle.backpressure().sent(), 2);
+    handle.join().unwrap();
+  }
+
+  #[test]
+  fn join() {
+    let mut handle = SmartMeter::new(io::repeat(0), Dlms::new(KEY)).spawn(1);
+
+    assert!(matches!(handle.next(), Some(Err(Error::LikelyBaudMismatch))));
+    assert!(!handle.is_finished());
+    handle.join().unwrap();
+  }
+
+  #[test]
+  fn lossy() {
+    let handle = SmartMeter::new(io::repeat(0), Dlms::new(KEY)).spawn_lossy(1);
+
+    while handle.backpressure().dropped() == 0 {
+      thread::yield_now();
+    }
+    assert_eq!(handle.backpressure().sent(), 1);
+    handle.join().unwrap();
+  }
+}
@@ -0,0 +1,41 @@
+/// Statistics collected while decoding.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Stats {
+  pub(crate) bytes_read: u64,
+  pub(crate) useful_bytes: u64,
+  pub(crate) decryption_failures: u64,
+  pub(crate) skipped_frames: u64,
+}
+
+impl Stats {
+  /// Total number of bytes read or pushed.
+  pub fn bytes_read(&self) -> u64 {
+    self.bytes_read
+  }
+
+  /// Number of bytes belonging to frames which were decoded into a reading.
+  pub fn useful_bytes(&self) -> u64 {
+    self.useful_bytes
+  }
+
+  /// Number of readings which failed to decrypt, including skipped ones.
+  pub fn decryption_failures(&self) -> u64 {
+    self.decryption_failures
+  }
+
+  /// Number of frames discarded without decrypting, see [`Decoder::skip_frame`](crate::Decoder::skip_frame).
+  pub fn skipped_frames(&self) -> u64 {
+    self.skipped_frames
+  }
+
+  /// The ratio of useful bytes to bytes read.
+  ///
+  /// A low value indicates a lot of resynchronization, e.g. due to a misconfigured line.
+  pub fn efficiency(&self) -> Option<f64> {
+    if self.bytes_read == 0 {
+      return None
+    }
+
+    Some(self.useful_bytes as f64 / self.bytes_read as f64)
+  }
+}
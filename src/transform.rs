@@ -0,0 +1,216 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use dlms_cosem::{Data, ObisCode, ObisMap};
+
+use crate::convert::Conversion;
+use crate::value::{as_f64, with_f64};
+
+/// A transformation applied to every reading before it is yielded.
+pub trait Transform {
+  fn apply(&mut self, obis_map: &mut ObisMap);
+}
+
+/// Applies a single conversion, see [`SmartMeter::with_conversion`](crate::SmartMeter::with_conversion).
+#[derive(Debug, Clone)]
+pub(crate) struct Convert(pub(crate) ObisCode, pub(crate) Conversion);
+
+impl Transform for Convert {
+  fn apply(&mut self, obis_map: &mut ObisMap) {
+    obis_map.convert(&self.0, self.1);
+  }
+}
+
+/// Rejects outliers by comparing values against the running median of the last readings.
+///
+/// Once `window` values have been seen for a code, a value deviating more than
+/// `max_deviation` percent from their median is replaced with the median.
+/// All values, including rejected ones, are added to the window so that
+/// the median follows genuine changes in level.
+///
+/// Since the deviation is relative, every value differing from a median of zero is replaced,
+/// e.g. the power import while exporting. Set a [`min_deviation`](Self::min_deviation) for such codes.
+#[derive(Debug, Clone)]
+pub struct MedianFilter {
+  window: usize,
+  max_deviation: f64,
+  min_deviation: f64,
+  history: BTreeMap<ObisCode, VecDeque<f64>>,
+}
+
+impl MedianFilter {
+  /// Create a filter for the given codes.
+  pub fn new(codes: impl IntoIterator<Item = ObisCode>, window: usize, max_deviation: f64) -> Self {
+    let history = codes.into_iter().map(|code| (code, VecDeque::with_capacity(window))).collect();
+    Self { window, max_deviation, min_deviation: 0.0, history }
+  }
+
+  /// Never replace values deviating at most `min_deviation` from the median, in the unit of the value.
+  ///
+  /// Defaults to `0.0`, i.e. only the relative `max_deviation` applies.
+  pub fn min_deviation(mut self, min_deviation: f64) -> Self {
+    self.min_deviation = min_deviation;
+    self
+  }
+
+  fn median(values: &VecDeque<f64>) -> f64 {
+    let mut values = values.iter().copied().collect::<Vec<_>>();
+    values.sort_by(f64::total_cmp);
+
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+      (values[mid - 1] + values[mid]) / 2.0
+    } else {
+      values[mid]
+    }
+  }
+}
+
+impl Transform for MedianFilter {
+  fn apply(&mut self, obis_map: &mut ObisMap) {
+    for (code, history) in self.history.iter_mut() {
+      let value = match obis_map.get(code).and_then(|reg| as_f64(reg.value())) {
+        Some(value) => value,
+        None => continue,
+      };
+
+      if self.window > 0 && history.len() == self.window {
+        let median = Self::median(history);
+
+        let max_deviation = (median.abs() * self.max_deviation / 100.0).max(self.min_deviation);
+        if (value - median).abs() > max_deviation {
+          obis_map.convert(code, |data| with_f64(&data, median));
+        }
+
+        history.pop_front();
+      }
+
+      if self.window > 0 {
+        history.push_back(value);
+      }
+    }
+  }
+}
+
+/// Rounds `Float32` and `Float64` values to the given number of decimals.
+///
+/// Ties are rounded to even, e.g. `0.125` becomes `0.12` with two decimals.
+/// Since most decimal fractions are not exactly representable, the result is
+/// the closest float to the rounded decimal. Values too large to be scaled by the
+/// number of decimals are returned unchanged. Other variants are left untouched.
+#[derive(Debug, Clone, Copy)]
+pub struct RoundFloats(pub u8);
+
+impl Transform for RoundFloats {
+  fn apply(&mut self, obis_map: &mut ObisMap) {
+    let factor = 10f64.powi(self.0.into());
+    let round = |n: f64| match n * factor {
+      // Large values cannot be scaled, but have no decimals to round anyway.
+      scaled if scaled.is_finite() => scaled.round_ties_even() / factor,
+      _ => n,
+    };
+
+    let codes = obis_map.keys().cloned().collect::<Vec<_>>();
+    for code in codes {
+      obis_map.convert(&code, |data| match data {
+        Data::Float32(n) => Data::Float32(round(n.into()) as f32),
+        Data::Float64(n) => Data::Float64(round(n)),
+        data => data,
+      });
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use crate::fixture::{notification, obis_map};
+
+  const POWER: ObisCode = ObisCode::new(1, 0, 1, 7, 0, 255);
+
+  fn power(value: u32) -> ObisMap {
+    let mut data = vec![0x06];
+    data.extend(value.to_be_bytes());
+    obis_map(&notification(&[([1, 0, 1, 7, 0, 255], &data, None)]))
+  }
+
+  fn filter(filter: &mut MedianFilter, value: u32) -> f64 {
+    let mut obis_map = power(value);
+    filter.apply(&mut obis_map);
+    as_f64(obis_map[&POWER].value()).unwrap()
+  }
+
+  fn round(decimals: u8, data: &[u8]) -> Data {
+    let mut obis_map = obis_map(&notification(&[([1, 0, 1, 7, 0, 255], data, None)]));
+    RoundFloats(decimals).apply(&mut obis_map);
+    obis_map[&POWER].value().clone()
+  }
+
+  fn float64(n: f64) -> Vec<u8> {
+    [[0x18].as_slice(), &n.to_be_bytes()].concat()
+  }
+
+  #[test]
+  fn round_floats() {
+    assert_eq!(round(2, &float64(1.23456)), Data::Float64(1.23));
+    assert_eq!(round(0, &float64(-2.5)), Data::Float64(-2.0));
+    assert_eq!(round(2, &[[0x17].as_slice(), &1.23456f32.to_be_bytes()].concat()), Data::Float32(1.23));
+    assert_eq!(round(2, &[0x06, 0x00, 0x00, 0x04, 0xd2]), Data::DoubleLongUnsigned(1234));
+  }
+
+  #[test]
+  fn round_floats_ties() {
+    assert_eq!(round(2, &float64(0.125)), Data::Float64(0.12));
+    assert_eq!(round(2, &float64(0.375)), Data::Float64(0.38));
+  }
+
+  #[test]
+  fn round_floats_large() {
+    assert_eq!(round(255, &float64(1e60)), Data::Float64(1e60));
+    assert_eq!(round(255, &float64(0.5)), Data::Float64(0.5));
+    assert_eq!(round(2, &float64(f64::MAX)), Data::Float64(f64::MAX));
+  }
+
+  #[test]
+  fn median_filter() {
+    let mut median_filter = MedianFilter::new([POWER], 3, 50.0);
+
+    // Values are passed through until the window is full.
+    assert_eq!(filter(&mut median_filter, 100), 100.0);
+    assert_eq!(filter(&mut median_filter, 5000), 5000.0);
+    assert_eq!(filter(&mut median_filter, 120), 120.0);
+
+    // The median of 100, 5000 and 120 is 120.
+    assert_eq!(filter(&mut median_filter, 5000), 120.0);
+    // The median of 5000, 120 and 5000 is 5000, so the window follows the new level.
+    assert_eq!(filter(&mut median_filter, 4000), 4000.0);
+    // The median of 120, 5000 and 4000 is 4000.
+    assert_eq!(filter(&mut median_filter, 130), 4000.0);
+  }
+
+  #[test]
+  fn median_filter_even_window() {
+    let mut median_filter = MedianFilter::new([POWER], 2, 10.0);
+
+    filter(&mut median_filter, 100);
+    filter(&mut median_filter, 200);
+
+    // The median of 100 and 200 is 150.
+    assert_eq!(filter(&mut median_filter, 300), 150.0);
+    // The median of 200 and 300 is 250.
+    assert_eq!(filter(&mut median_filter, 260), 260.0);
+  }
+
+  #[test]
+  fn median_filter_zero_median() {
+    let mut median_filter = MedianFilter::new([POWER], 1, 50.0);
+    filter(&mut median_filter, 0);
+    assert_eq!(filter(&mut median_filter, 1), 0.0);
+
+    let mut median_filter = MedianFilter::new([POWER], 1, 50.0).min_deviation(10.0);
+    filter(&mut median_filter, 0);
+    assert_eq!(filter(&mut median_filter, 10), 10.0);
+    filter(&mut median_filter, 0);
+    assert_eq!(filter(&mut median_filter, 11), 0.0);
+  }
+}
@@ -0,0 +1,184 @@
+use std::io::{self, Read};
+
+/// The byte order of a length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endian {
+  Big,
+  Little,
+}
+
+/// A reader which strips length prefixes added by a bridge, e.g. a serial-to-IP converter.
+///
+/// Every message of `inner` is expected to start with its payload length, which is removed,
+/// so only the payload, usually an M-Bus frame, is returned. A read never spans multiple messages.
+/// See [`Decoder::with_transport_header`](crate::Decoder::with_transport_header) for other headers.
+#[derive(Debug)]
+pub struct LengthPrefixedTransport<R> {
+  inner: R,
+  endian: Endian,
+  prefix: [u8; 4],
+  prefix_bytes: usize,
+  prefix_read: usize,
+  remaining: usize,
+}
+
+impl<R> LengthPrefixedTransport<R> {
+  /// Create a reader for messages prefixed with a `prefix_bytes` long length in the given byte order.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `prefix_bytes` is not between 1 and 4.
+  pub fn new(inner: R, endian: Endian, prefix_bytes: usize) -> Self {
+    assert!((1..=4).contains(&prefix_bytes), "length prefix must be between 1 and 4 bytes");
+
+    Self { inner, endian, prefix: [0; 4], prefix_bytes, prefix_read: 0, remaining: 0 }
+  }
+
+  /// Get back the underlying reader.
+  pub fn into_inner(self) -> R {
+    self.inner
+  }
+
+  fn len(&self) -> usize {
+    let prefix = &self.prefix[..self.prefix_bytes];
+
+    let mut bytes = [0; 4];
+    let len = match self.endian {
+      Endian::Big => {
+        bytes[(4 - prefix.len())..].copy_from_slice(prefix);
+        u32::from_be_bytes(bytes)
+      },
+      Endian::Little => {
+        bytes[..prefix.len()].copy_from_slice(prefix);
+        u32::from_le_bytes(bytes)
+      },
+    };
+
+    len as usize
+  }
+}
+
+impl<R: Read> Read for LengthPrefixedTransport<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if buf.is_empty() {
+      return Ok(0)
+    }
+
+    while self.remaining == 0 {
+      // Keep partially read prefixes, so a timeout does not lose the framing.
+      while self.prefix_read < self.prefix_bytes {
+        let n = self.inner.read(&mut self.prefix[self.prefix_read..self.prefix_bytes])?;
+        if n == 0 {
+          return Ok(0)
+        }
+        self.prefix_read += n;
+      }
+
+      self.prefix_read = 0;
+      self.remaining = self.len();
+    }
+
+    let len = buf.len().min(self.remaining);
+    let n = self.inner.read(&mut buf[..len])?;
+    self.remaining -= n;
+    Ok(n)
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use std::collections::VecDeque;
+  use std::io::Cursor;
+
+  use dlms_cosem::Dlms;
+
+  use crate::SmartMeter;
+  use crate::fixture::{FRAMES, FRAME_1_LEN, KEY};
+
+  /// A reader returning the given chunks one at a time, or timing out for `None`.
+  struct Chunks(VecDeque<Option<&'static [u8]>>);
+
+  impl Read for Chunks {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+      match self.0.pop_front() {
+        Some(Some(chunk)) => {
+          let n = buf.len().min(chunk.len());
+          buf[..n].copy_from_slice(&chunk[..n]);
+          if n < chunk.len() {
+            self.0.push_front(Some(&chunk[n..]));
+          }
+          Ok(n)
+        },
+        Some(None) => Err(io::ErrorKind::TimedOut.into()),
+        None => Ok(0),
+      }
+    }
+  }
+
+  fn read_all(mut reader: impl Read) -> Vec<Vec<u8>> {
+    let mut reads = Vec::new();
+    let mut buf = [0; 16];
+
+    loop {
+      match reader.read(&mut buf) {
+        Ok(0) => return reads,
+        Ok(n) => reads.push(buf[..n].to_vec()),
+        Err(err) if err.kind() == io::ErrorKind::TimedOut => (),
+        Err(err) => panic!("{}", err),
+      }
+    }
+  }
+
+  #[test]
+  fn endian() {
+    let big = LengthPrefixedTransport::new(Cursor::new([0x00, 0x03, 1, 2, 3]), Endian::Big, 2);
+    assert_eq!(read_all(big), [vec![1, 2, 3]]);
+
+    let little = LengthPrefixedTransport::new(Cursor::new([0x03, 0x00, 0x00, 1, 2, 3]), Endian::Little, 3);
+    assert_eq!(read_all(little), [vec![1, 2, 3]]);
+
+    let single = LengthPrefixedTransport::new(Cursor::new([0x01, 1, 0x01, 2]), Endian::Big, 1);
+    assert_eq!(read_all(single), [vec![1], vec![2]]);
+  }
+
+  #[test]
+  fn split_prefix() {
+    let chunks = Chunks(VecDeque::from([Some([0x00].as_slice()), None, Some(&[0x02, 1]), None, Some(&[2])]));
+    let transport = LengthPrefixedTransport::new(chunks, Endian::Big, 2);
+    assert_eq!(read_all(transport), [vec![1], vec![2]]);
+  }
+
+  #[test]
+  fn empty_messages() {
+    let transport = LengthPrefixedTransport::new(Cursor::new([0x00, 0x00, 0x00, 0x01, 1, 0x00, 0x00]), Endian::Big, 2);
+    assert_eq!(read_all(transport), [vec![1]]);
+  }
+
+  #[test]
+  fn eof_in_prefix() {
+    let mut transport = LengthPrefixedTransport::new(Cursor::new([0x00, 0x01, 1, 0x00]), Endian::Big, 2);
+    let mut buf = [0; 4];
+
+    assert_eq!(transport.read(&mut buf).unwrap(), 1);
+    assert_eq!(transport.read(&mut buf).unwrap(), 0);
+    assert_eq!(transport.prefix_read, 1);
+  }
+
+  #[test]
+  fn read_within_message() {
+    let transport = LengthPrefixedTransport::new(Cursor::new([0x02, 1, 2, 0x03, 3, 4, 5, 0x01, 6]), Endian::Big, 1);
+    assert_eq!(read_all(transport), [vec![1, 2], vec![3, 4, 5], vec![6]]);
+  }
+
+  #[test]
+  fn frames() {
+    let (frame_1, frame_2) = FRAMES.split_at(FRAME_1_LEN);
+    let bytes = [&u16::to_be_bytes(frame_1.len() as u16), frame_1, &u16::to_be_bytes(frame_2.len() as u16), frame_2].concat();
+
+    let transport = LengthPrefixedTransport::new(Cursor::new(bytes), Endian::Big, 2);
+    let mut smart_meter = SmartMeter::new(transport, Dlms::new(KEY));
+    assert_eq!(smart_meter.read_reading().unwrap().len(), 15);
+  }
+}
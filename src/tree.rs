@@ -0,0 +1,356 @@
+use dlms_cosem::DateTime;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::Error;
+
+pub(crate) const MAX_DEPTH: usize = 32;
+
+/// A generic tree of DLMS data, for inspecting APDUs not covered by `ObisMap`.
+///
+/// Arrays, compact arrays and structures become lists, all integer types become `Integer`
+/// or `Unsigned` and date-times are formatted as strings.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize), serde(untagged))]
+pub enum DataTree {
+  Null,
+  Bool(bool),
+  Integer(i64),
+  Unsigned(u64),
+  Float(f64),
+  String(String),
+  Bytes(Vec<u8>),
+  List(Vec<DataTree>),
+}
+
+impl DataTree {
+  /// Decode the notification body of a plain data-notification APDU, see [`decrypt_apdu`](crate::decrypt_apdu).
+  pub fn from_apdu(apdu: &[u8]) -> Result<Self, Error> {
+    match apdu {
+      [0x0f, _, _, _, _, date_time_len, rest @ ..] => {
+        let body = rest.get(usize::from(*date_time_len)..).ok_or(Error::InvalidFormat)?;
+        Self::parse(body)
+      },
+      _ => Err(Error::InvalidFormat),
+    }
+  }
+
+  /// Parse an octet string holding a date-time, the usual encoding of clocks and capture times.
+  pub(crate) fn as_date_time(&self) -> Option<DateTime> {
+    match self {
+      Self::Bytes(bytes) if bytes.len() == 12 => DateTime::parse(bytes).ok().map(|(_, date_time)| date_time),
+      _ => None,
+    }
+  }
+
+  /// Decode a single A-XDR encoded DLMS data value.
+  pub fn parse(bytes: &[u8]) -> Result<Self, Error> {
+    match parse(bytes, 0) {
+      Some((_, tree)) => Ok(tree),
+      None => Err(Error::InvalidFormat),
+    }
+  }
+}
+
+fn length(input: &[u8]) -> Option<(&[u8], usize)> {
+  let (&first, input) = input.split_first()?;
+
+  match first {
+    0x00..=0x7f => Some((input, usize::from(first))),
+    0x81..=0x84 => {
+      let len = usize::from(first & 0x7f);
+      let bytes = input.get(..len)?;
+      let length = bytes.iter().fold(0, |n, &b| (n << 8) | usize::from(b));
+      Some((&input[len..], length))
+    },
+    _ => None,
+  }
+}
+
+/// Split an encoded array or structure into the encodings of its elements.
+///
+/// Like `dlms_cosem`, the number of elements is a single byte.
+pub(crate) fn split_list(input: &[u8]) -> Option<Vec<&[u8]>> {
+  let (mut input, count) = match input {
+    [1 | 2, count, input @ ..] => (input, usize::from(*count)),
+    _ => return None,
+  };
+
+  let mut elements = Vec::with_capacity(count.min(input.len()));
+  for _ in 0..count {
+    let (next_input, _) = parse(input, 1)?;
+    elements.push(&input[..(input.len() - next_input.len())]);
+    input = next_input;
+  }
+  Some(elements)
+}
+
+fn take(input: &[u8], n: usize) -> Option<(&[u8], &[u8])> {
+  if input.len() < n {
+    return None
+  }
+
+  let (bytes, input) = input.split_at(n);
+  Some((input, bytes))
+}
+
+fn take_array<const N: usize>(input: &[u8]) -> Option<(&[u8], [u8; N])> {
+  let (input, bytes) = take(input, N)?;
+  Some((input, bytes.try_into().ok()?))
+}
+
+/// The type of the elements of a compact array.
+enum TypeDescription {
+  Simple(u8),
+  Array(usize, Box<TypeDescription>),
+  Structure(Vec<TypeDescription>),
+}
+
+/// Parse the type description of a compact array.
+///
+/// Types without contents, i.e. null data and empty arrays or structures, are rejected,
+/// since parsing the contents would never advance.
+fn type_description(input: &[u8], depth: usize) -> Option<(&[u8], TypeDescription)> {
+  if depth > MAX_DEPTH {
+    return None
+  }
+
+  let (&tag, input) = input.split_first()?;
+
+  Some(match tag {
+    1 => {
+      let (input, count) = take_array(input)?;
+      let count = u16::from_be_bytes(count);
+      if count == 0 {
+        return None
+      }
+      let (input, element) = type_description(input, depth + 1)?;
+      (input, TypeDescription::Array(count.into(), Box::new(element)))
+    },
+    2 => {
+      let (mut input, count) = length(input)?;
+      let mut elements = Vec::with_capacity(count.min(input.len()));
+      for _ in 0..count {
+        let (next_input, element) = type_description(input, depth + 1)?;
+        elements.push(element);
+        input = next_input;
+      }
+      if elements.is_empty() {
+        return None
+      }
+      (input, TypeDescription::Structure(elements))
+    },
+    // Null data has no contents, and compact arrays cannot be nested.
+    0 | 19 => return None,
+    tag => (input, TypeDescription::Simple(tag)),
+  })
+}
+
+/// Parse a value of a compact array, which is encoded without tags.
+fn parse_untagged<'i>(input: &'i [u8], description: &TypeDescription, depth: usize) -> Option<(&'i [u8], DataTree)> {
+  if depth > MAX_DEPTH {
+    return None
+  }
+
+  let (count, elements) = match description {
+    TypeDescription::Simple(tag) => return parse_value(*tag, input, depth),
+    TypeDescription::Array(count, element) => (*count, std::slice::from_ref(&**element)),
+    TypeDescription::Structure(elements) => (elements.len(), elements.as_slice()),
+  };
+
+  let mut input = input;
+  let mut items = Vec::with_capacity(count.min(input.len()));
+  for element in elements.iter().cycle().take(count) {
+    let (next_input, item) = parse_untagged(input, element, depth + 1)?;
+    items.push(item);
+    input = next_input;
+  }
+  Some((input, DataTree::List(items)))
+}
+
+fn parse(input: &[u8], depth: usize) -> Option<(&[u8], DataTree)> {
+  if depth > MAX_DEPTH {
+    return None
+  }
+
+  let (&tag, input) = input.split_first()?;
+  parse_value(tag, input, depth)
+}
+
+fn parse_value(tag: u8, input: &[u8], depth: usize) -> Option<(&[u8], DataTree)> {
+  Some(match tag {
+    0 => (input, DataTree::Null),
+    // Array and structure.
+    1 | 2 => {
+      let (mut input, count) = length(input)?;
+      let mut items = Vec::new();
+      for _ in 0..count {
+        let (next_input, item) = parse(input, depth + 1)?;
+        items.push(item);
+        input = next_input;
+      }
+      (input, DataTree::List(items))
+    },
+    3 => {
+      let (input, [b]) = take_array(input)?;
+      (input, DataTree::Bool(b != 0))
+    },
+    // Bit string, length in bits.
+    4 => {
+      let (input, bits) = length(input)?;
+      let (input, bytes) = take(input, bits.div_ceil(8))?;
+      (input, DataTree::Bytes(bytes.to_vec()))
+    },
+    5 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Integer(i32::from_be_bytes(n).into()))
+    },
+    6 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Unsigned(u32::from_be_bytes(n).into()))
+    },
+    // Octet string.
+    9 => {
+      let (input, len) = length(input)?;
+      let (input, bytes) = take(input, len)?;
+      (input, DataTree::Bytes(bytes.to_vec()))
+    },
+    // Visible string and UTF-8 string.
+    10 | 12 => {
+      let (input, len) = length(input)?;
+      let (input, bytes) = take(input, len)?;
+      (input, DataTree::String(String::from_utf8_lossy(bytes).into_owned()))
+    },
+    // BCD, unsigned and enum.
+    13 | 17 | 22 => {
+      let (input, [n]) = take_array(input)?;
+      (input, DataTree::Unsigned(n.into()))
+    },
+    15 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Integer(i8::from_be_bytes(n).into()))
+    },
+    16 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Integer(i16::from_be_bytes(n).into()))
+    },
+    18 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Unsigned(u16::from_be_bytes(n).into()))
+    },
+    20 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Integer(i64::from_be_bytes(n)))
+    },
+    21 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Unsigned(u64::from_be_bytes(n)))
+    },
+    23 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Float(f32::from_be_bytes(n).into()))
+    },
+    24 => {
+      let (input, n) = take_array(input)?;
+      (input, DataTree::Float(f64::from_be_bytes(n)))
+    },
+    // Date-time.
+    25 => {
+      let (input, bytes) = take(input, 12)?;
+      let tree = match DateTime::parse(bytes) {
+        Ok((_, date_time)) => DataTree::String(date_time.to_string()),
+        Err(_) => DataTree::Bytes(bytes.to_vec()),
+      };
+      (input, tree)
+    },
+    // Date.
+    26 => {
+      let (input, bytes) = take(input, 5)?;
+      (input, DataTree::Bytes(bytes.to_vec()))
+    },
+    // Time.
+    27 => {
+      let (input, bytes) = take(input, 4)?;
+      (input, DataTree::Bytes(bytes.to_vec()))
+    },
+    // Compact array, i.e. the type description of its elements followed by their values without tags.
+    19 => {
+      let (input, description) = type_description(input, depth + 1)?;
+      let (input, len) = length(input)?;
+      let (input, mut contents) = take(input, len)?;
+
+      let mut items = Vec::new();
+      while !contents.is_empty() {
+        let (next_contents, item) = parse_untagged(contents, &description, depth + 1)?;
+        items.push(item);
+        contents = next_contents;
+      }
+      (input, DataTree::List(items))
+    },
+    _ => return None,
+  })
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn split_list_huge_count() {
+    assert_eq!(split_list(&[0x02, 0xff, 0x00]), None);
+  }
+
+  #[test]
+  fn split_list_single_byte_count() {
+    let mut list = vec![0x02, 0x81];
+    list.extend([0x00].repeat(0x81));
+    assert_eq!(split_list(&list).map(|elements| elements.len()), Some(0x81));
+  }
+
+  #[test]
+  fn split_list_elements() {
+    let elements = split_list(&[0x02, 0x02, 0x12, 0x00, 0x64, 0x09, 0x01, 0xaa]).unwrap();
+    assert_eq!(elements, [[0x12, 0x00, 0x64].as_slice(), &[0x09, 0x01, 0xaa]]);
+  }
+
+  #[test]
+  fn parse_date_time() {
+    let mut bytes = vec![0x19];
+    bytes.extend([0x07, 0xe5, 0x09, 0x0b, 0x06, 0x09, 0x0d, 0x14, 0x00, 0xff, 0x88, 0x80]);
+    assert!(matches!(DataTree::parse(&bytes), Ok(DataTree::String(_))));
+  }
+
+  #[test]
+  fn parse_compact_array() {
+    // Structures of a long-unsigned and an octet string.
+    let bytes = [0x13, 0x02, 0x02, 0x12, 0x09, 0x08, 0x00, 0x64, 0x02, 0xaa, 0xbb, 0x00, 0xc8, 0x00];
+    assert_eq!(DataTree::parse(&bytes).unwrap(), DataTree::List(vec![
+      DataTree::List(vec![DataTree::Unsigned(100), DataTree::Bytes(vec![0xaa, 0xbb])]),
+      DataTree::List(vec![DataTree::Unsigned(200), DataTree::Bytes(vec![])]),
+    ]));
+
+    // Arrays of two longs.
+    let bytes = [0x13, 0x01, 0x00, 0x02, 0x10, 0x08, 0xff, 0xff, 0x00, 0x01, 0xff, 0xfe, 0x00, 0x02];
+    assert_eq!(DataTree::parse(&bytes).unwrap(), DataTree::List(vec![
+      DataTree::List(vec![DataTree::Integer(-1), DataTree::Integer(1)]),
+      DataTree::List(vec![DataTree::Integer(-2), DataTree::Integer(2)]),
+    ]));
+
+    assert_eq!(DataTree::parse(&[0x13, 0x12, 0x00]).unwrap(), DataTree::List(vec![]));
+  }
+
+  #[test]
+  fn parse_compact_array_invalid() {
+    // Incomplete element, null data, empty array and structure, nested compact array and unknown type.
+    for bytes in [
+      &[0x13, 0x12, 0x03, 0x00, 0x01, 0x00][..],
+      &[0x13, 0x00, 0x01, 0x00],
+      &[0x13, 0x01, 0x00, 0x00, 0x12, 0x02, 0x00, 0x01],
+      &[0x13, 0x02, 0x00, 0x01, 0x00],
+      &[0x13, 0x13, 0x12, 0x02, 0x00, 0x01],
+      &[0x13, 0xff, 0x01, 0x00],
+    ] {
+      assert!(matches!(DataTree::parse(bytes), Err(Error::InvalidFormat)), "{:02x?}", bytes);
+    }
+  }
+}
@@ -0,0 +1,73 @@
+use dlms_cosem::Unit;
+
+/// Extensions for `Unit`.
+pub trait UnitExt {
+  /// Get the unit of the product of quantities in `self` and `other`,
+  /// e.g. [`Unit::VoltAmpere`] for [`Unit::Volt`] and [`Unit::Ampere`].
+  ///
+  /// Returns `None` if the product has no unit of its own.
+  fn multiply(&self, other: &Unit) -> Option<Unit>;
+}
+
+fn product(a: Unit, b: Unit) -> Option<Unit> {
+  Some(match (a, b) {
+    (Unit::Volt, Unit::Ampere) => Unit::VoltAmpere,
+    (Unit::Ohm, Unit::Ampere) => Unit::Volt,
+    (Unit::Siemens, Unit::Volt) => Unit::Ampere,
+    (Unit::Farad, Unit::Volt) => Unit::Coulomb,
+    (Unit::Henry, Unit::Ampere) => Unit::Weber,
+    (Unit::Volt, Unit::Coulomb) => Unit::Joule,
+    (Unit::Ampere, Unit::Second) => Unit::Coulomb,
+    (Unit::Ampere, Unit::Hour) => Unit::AmpereHour,
+    (Unit::Watt, Unit::Second) => Unit::Joule,
+    (Unit::Watt, Unit::Hour) => Unit::WattHour,
+    (Unit::VoltAmpere, Unit::Hour) => Unit::VoltAmpereHour,
+    (Unit::Var, Unit::Hour) => Unit::VarHour,
+    (Unit::JoulePerHour, Unit::Hour) => Unit::Joule,
+    (Unit::CubicMeterPerHour, Unit::Hour) => Unit::CubicMeter,
+    (Unit::CubicMeterPerHourCorrected, Unit::Hour) => Unit::CubicMeterCorrected,
+    (Unit::CubicMeterPerDay, Unit::Day) => Unit::CubicMeter,
+    (Unit::CubicMeterPerDayCorrected, Unit::Day) => Unit::CubicMeterCorrected,
+    (Unit::MeterPerSecond, Unit::Second) => Unit::Meter,
+    (Unit::KilogrammPerSecond, Unit::Second) => Unit::Kilogramm,
+    (Unit::Pascal, Unit::Second) => Unit::PascalSecond,
+    (Unit::Newton, Unit::Meter) => Unit::Newtonmeter,
+    (Unit::Ohm, Unit::Meter) => Unit::OhmMeter,
+    (Unit::VoltPerMeter, Unit::Meter) => Unit::Volt,
+    (Unit::AmperePerMeter, Unit::Meter) => Unit::Ampere,
+    (Unit::WattHourPerCubicMeter, Unit::CubicMeter) => Unit::WattHour,
+    (Unit::JoulePerCubicMeter, Unit::CubicMeter) => Unit::Joule,
+    (Unit::JoulePerKilogramm, Unit::Kilogramm) => Unit::Joule,
+    _ => return None,
+  })
+}
+
+impl UnitExt for Unit {
+  fn multiply(&self, other: &Unit) -> Option<Unit> {
+    product(*self, *other).or_else(|| product(*other, *self))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn multiply() {
+    assert_eq!(Unit::Volt.multiply(&Unit::Ampere), Some(Unit::VoltAmpere));
+    assert_eq!(Unit::Watt.multiply(&Unit::Hour), Some(Unit::WattHour));
+  }
+
+  #[test]
+  fn multiply_commutative() {
+    assert_eq!(Unit::Ampere.multiply(&Unit::Volt), Some(Unit::VoltAmpere));
+    assert_eq!(Unit::Hour.multiply(&Unit::Watt), Some(Unit::WattHour));
+  }
+
+  #[test]
+  fn multiply_incompatible() {
+    assert_eq!(Unit::Volt.multiply(&Unit::Volt), None);
+    assert_eq!(Unit::WattHour.multiply(&Unit::Hour), None);
+    assert_eq!(Unit::Watt.multiply(&Unit::Day), None);
+  }
+}
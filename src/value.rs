@@ -0,0 +1,144 @@
+use std::borrow::Cow;
+
+use dlms_cosem::Data;
+
+/// Scale a raw integer value by `10^scaler`, see [`apply_scaler_f64`].
+pub fn apply_scaler(raw: i64, scaler: i8) -> f64 {
+  apply_scaler_f64(raw as f64, scaler)
+}
+
+/// Scale a value by `10^scaler`.
+///
+/// Negative scalers divide, like `dlms_cosem`, to avoid rounding errors, e.g. `3` with
+/// a scaler of `-1` is exactly `0.3` instead of `3 * 0.1`.
+pub fn apply_scaler_f64(value: f64, scaler: i8) -> f64 {
+  let factor = 10f64.powi(i32::from(scaler).abs());
+  if scaler < 0 { value / factor } else { value * factor }
+}
+
+/// Get a numeric or enum `Data` value as `f64`, returning `None` for other types.
+pub fn as_f64(value: &Data) -> Option<f64> {
+  Some(match *value {
+    Data::Integer(n) => n.into(),
+    Data::Unsigned(n) => n.into(),
+    Data::Long(n) => n.into(),
+    Data::LongUnsigned(n) => n.into(),
+    Data::DoubleLong(n) => n.into(),
+    Data::DoubleLongUnsigned(n) => n.into(),
+    Data::Long64(n) => n as f64,
+    Data::Long64Unsigned(n) => n as f64,
+    Data::Float32(n) => n.into(),
+    Data::Float64(n) => n,
+    Data::Enum(n) => n.into(),
+    _ => return None,
+  })
+}
+
+/// Get a `Data` value as an integer, also accepting strings of ASCII digits.
+pub(crate) fn as_u64(value: &Data) -> Option<u64> {
+  match value {
+    Data::Unsigned(n) => Some((*n).into()),
+    Data::LongUnsigned(n) => Some((*n).into()),
+    Data::DoubleLongUnsigned(n) => Some((*n).into()),
+    Data::Long64Unsigned(n) => Some(*n),
+    Data::Enum(n) => Some((*n).into()),
+    Data::OctetString(s) => std::str::from_utf8(s).ok()?.trim().parse().ok(),
+    Data::Utf8String(s) => s.trim().parse().ok(),
+    _ => None,
+  }
+}
+
+/// Replace a numeric `Data` value with `n`, keeping its type.
+pub(crate) fn with_f64(value: &Data, n: f64) -> Data {
+  match *value {
+    Data::Integer(_) => Data::Integer(n.round() as i8),
+    Data::Unsigned(_) => Data::Unsigned(n.round() as u8),
+    Data::Long(_) => Data::Long(n.round() as i16),
+    Data::LongUnsigned(_) => Data::LongUnsigned(n.round() as u16),
+    Data::DoubleLong(_) => Data::DoubleLong(n.round() as i32),
+    Data::DoubleLongUnsigned(_) => Data::DoubleLongUnsigned(n.round() as u32),
+    Data::Long64(_) => Data::Long64(n.round() as i64),
+    Data::Long64Unsigned(_) => Data::Long64Unsigned(n.round() as u64),
+    Data::Float32(_) => Data::Float32(n as f32),
+    Data::Float64(_) => Data::Float64(n),
+    Data::Enum(_) => Data::Enum(n.round() as u8),
+    ref value => value.clone(),
+  }
+}
+
+/// Format a `Data` value, borrowing where possible.
+///
+/// Strings are returned as is, other values are formatted into a new `String`.
+pub fn format_value(value: &Data) -> Cow<'_, str> {
+  match value {
+    Data::Utf8String(s) => Cow::Borrowed(s.as_str()),
+    Data::DateTime(date_time) => Cow::Owned(date_time.to_string()),
+    Data::Float32(n) => Cow::Owned(n.to_string()),
+    Data::Float64(n) => Cow::Owned(n.to_string()),
+    Data::Integer(n) => Cow::Owned(n.to_string()),
+    Data::Unsigned(n) => Cow::Owned(n.to_string()),
+    Data::Long(n) => Cow::Owned(n.to_string()),
+    Data::LongUnsigned(n) => Cow::Owned(n.to_string()),
+    Data::DoubleLong(n) => Cow::Owned(n.to_string()),
+    Data::DoubleLongUnsigned(n) => Cow::Owned(n.to_string()),
+    Data::Long64(n) => Cow::Owned(n.to_string()),
+    Data::Long64Unsigned(n) => Cow::Owned(n.to_string()),
+    data => Cow::Owned(format!("{:?}", data)),
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  use dlms_cosem::ObisCode;
+
+  use crate::apply_scalers;
+  use crate::fixture::{notification, obis_map};
+
+  const CELSIUS: u8 = 9;
+
+  #[test]
+  fn format_signed() {
+    assert_eq!(format_value(&Data::Integer(-5)), "-5");
+    assert_eq!(format_value(&Data::Long(-1234)), "-1234");
+    assert_eq!(format_value(&Data::DoubleLong(-123456)), "-123456");
+    assert_eq!(format_value(&Data::Long64(-1)), "-1");
+  }
+
+  #[test]
+  fn format_scaled_signed() {
+    let apdu = notification(&[
+      ([0, 0, 96, 9, 0, 255], &[0x0f, 0xfb], Some((-1, CELSIUS))),
+      ([0, 1, 96, 9, 0, 255], &[0x10, 0xff, 0x38], Some((-1, CELSIUS))),
+      ([0, 2, 96, 9, 0, 255], &[0x05, 0xff, 0xff, 0xff, 0x6a], Some((-2, CELSIUS))),
+      ([0, 3, 96, 9, 0, 255], &[0x10, 0xff, 0xfe], Some((2, CELSIUS))),
+    ]);
+    let mut registers = obis_map(&apdu);
+    apply_scalers(&mut registers, &apdu).unwrap();
+
+    let value = |b: u8| format_value(registers[&ObisCode::new(0, b, 96, 9, 0, 255)].value()).into_owned();
+    assert_eq!(value(0), "-0.5");
+    assert_eq!(value(1), "-20");
+    assert_eq!(value(2), "-1.5");
+    assert_eq!(value(3), "-200");
+  }
+
+  #[test]
+  fn scaler_exponents() {
+    for (scaler, expected) in [(-3, 1.234), (-2, 12.34), (-1, 123.4), (0, 1234.0), (1, 12340.0), (2, 123400.0), (3, 1234000.0)] {
+      assert_eq!(apply_scaler(1234, scaler), expected, "scaler {}", scaler);
+      assert_eq!(apply_scaler(-1234, scaler), -expected, "scaler {}", scaler);
+      assert_eq!(apply_scaler_f64(1234.0, scaler), expected, "scaler {}", scaler);
+    }
+  }
+
+  #[test]
+  fn scaler_large_magnitudes() {
+    assert_eq!(apply_scaler(i64::from(u32::MAX), -3), 4294967.295);
+    // `i8::MIN` must not overflow when taking the absolute value.
+    assert!((apply_scaler(1, -128) / 1e-128 - 1.0).abs() < 1e-12);
+    assert!((apply_scaler(1, 127) / 1e127 - 1.0).abs() < 1e-12);
+    assert_eq!(apply_scaler(0, -128), 0.0);
+  }
+}
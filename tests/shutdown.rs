@@ -0,0 +1,76 @@
+use std::io::{self, Cursor, Read};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use dlms_cosem::Dlms;
+use smart_meter::{Error, SmartMeter};
+
+#[path = "../examples/shutdown/run.rs"]
+mod run;
+
+const FRAMES: &[u8] = include_bytes!("../benches/fixtures/frames.bin");
+const KEY: [u8; 16] = [0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe, 0xde, 0xaf, 0xbe, 0xef, 0xca, 0xfe, 0xba, 0xbe];
+
+/// A meter which sends one reading and then stays silent, like a serial port with a read timeout.
+///
+/// Once silent, it requests a shutdown, like pressing Ctrl-C after the first reading.
+struct Meter {
+  bytes: &'static [u8],
+  running: Arc<AtomicBool>,
+}
+
+impl Read for Meter {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    if self.bytes.is_empty() {
+      self.running.store(false, Ordering::SeqCst);
+      thread::sleep(Duration::from_millis(10));
+      return Err(io::ErrorKind::TimedOut.into())
+    }
+
+    let len = buf.len().min(self.bytes.len());
+    buf[..len].copy_from_slice(&self.bytes[..len]);
+    self.bytes = &self.bytes[len..];
+    Ok(len)
+  }
+}
+
+#[test]
+fn shutdown() {
+  let running = Arc::new(AtomicBool::new(true));
+  let meter = Meter { bytes: FRAMES, running: Arc::clone(&running) };
+  let handle = SmartMeter::new(meter, Dlms::new(KEY)).spawn(1);
+
+  let mut out = Vec::new();
+  // Returns only once the reader thread was joined.
+  run::print_readings(handle, &running, &mut out).unwrap();
+
+  let out = String::from_utf8(out).unwrap();
+  assert_eq!(out.matches("1-0:1.8.0*255:").count(), 1);
+  assert!(out.ends_with("\n\n"));
+}
+
+#[test]
+fn exhausted() {
+  let running = AtomicBool::new(true);
+  let handle = SmartMeter::new(Cursor::new([FRAMES, FRAMES].concat()), Dlms::new(KEY)).spawn(1);
+
+  let mut out = Vec::new();
+  run::print_readings(handle, &running, &mut out).unwrap();
+
+  assert_eq!(String::from_utf8(out).unwrap().matches("1-0:1.8.0*255:").count(), 2);
+  assert!(running.load(Ordering::SeqCst));
+}
+
+#[test]
+fn reader_handle() {
+  let running = Arc::new(AtomicBool::new(true));
+  let mut handle = SmartMeter::new(Meter { bytes: FRAMES, running }, Dlms::new(KEY)).spawn(1);
+
+  assert!(matches!(handle.next(), Some(Ok(_))));
+  assert!(matches!(handle.next(), Some(Err(Error::Io(err))) if err.kind() == io::ErrorKind::TimedOut));
+  assert!(!handle.is_finished());
+
+  handle.join().unwrap();
+}